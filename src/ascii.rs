@@ -0,0 +1,99 @@
+use crate::cell::{Cell, Grid};
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::dither::{self, Dither};
+use crate::{Mode, RenderOptions, Renderer};
+use image::DynamicImage;
+
+/// Density ramp from empty to dense. Dense glyphs stand for dark pixels,
+/// like ink on paper.
+pub const RAMP: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+/// The ASCII density ramp, as a [`Renderer`]; see [`render`].
+pub struct Ascii;
+
+impl Renderer for Ascii {
+    fn cell_size(&self) -> (u32, u32) {
+        (1, 2)
+    }
+
+    fn render(&self, img: &DynamicImage, opts: &RenderOptions) -> Grid {
+        render(img, opts)
+    }
+}
+
+/// Renders an image as plain ASCII: each 1x2 cell becomes the ramp glyph
+/// matching its average luma, Floyd-Steinberg dithered across cells unless
+/// `opts.dither` is off. No escape codes are emitted.
+///
+/// With `opts.ascii_ramp_invert` the ramp runs the other way, so `@` marks
+/// the brightest pixels, which suits light text on a dark terminal.
+pub fn render(img: &DynamicImage, opts: &RenderOptions) -> Grid {
+    render_ramp(img, opts, Mode::Ascii, &RAMP, |luma| {
+        // Dense glyphs are dark pixels, or bright ones for light-on-dark
+        if opts.ascii_ramp_invert != opts.invert_diffusion { luma } else { 1.0 - luma }
+    })
+}
+
+/// Renders an image one glyph of `ramp` per 1x2 cell, picked by how far
+/// along it `fill` puts the cell's average luma (0 the first glyph, 1 the
+/// last), Floyd-Steinberg dithered across cells unless `opts.dither` is off.
+/// The glyphs carry no color; the ASCII and eighths modes are both drawn
+/// this way.
+pub(crate) fn render_ramp(img: &DynamicImage, opts: &RenderOptions, mode: Mode, ramp: &[char], fill: impl Fn(f32) -> f32) -> Grid {
+    let mut resized = crate::fit_image(img, opts, mode);
+
+    if opts.sharpen_after_resize() {
+        resized = image::imageops::filter3x3(&resized, &crate::SHARPEN);
+    }
+
+    let (width, height) = resized.dimensions();
+    let rows = height.div_ceil(2);
+    let top = (ramp.len() - 1) as f32;
+    let mut error_diffusion = vec![vec![0.0f32; width as usize]; rows as usize];
+    let mut grid = Grid::new();
+
+    for y in (0..height).step_by(2) {
+        let mut row = Vec::new();
+
+        for x in 0..width {
+            if crate::key::keyed_cell(&resized, opts, (x, y), (1, 2)) {
+                row.push(Cell::blank());
+                continue;
+            }
+
+            // Average the cell (in linear light by default), then take luma
+            // in sRGB
+            let pixels: Vec<_> = (y..(y + 2).min(height)).map(|py| resized.get_pixel(x, py)).collect();
+            let colors: Vec<_> = pixels
+                .iter()
+                .map(|p| (srgb_to_linear(p[0] as f32 / 255.0), srgb_to_linear(p[1] as f32 / 255.0), srgb_to_linear(p[2] as f32 / 255.0)))
+                .collect();
+            let alphas: Vec<_> = pixels.iter().map(|p| p[3] as f32 / 255.0).collect();
+            // A cell of only transparent pixels has no color to draw
+            let Some(mean) = opts.cell_mean(&colors, &alphas, colors.len() as f32) else {
+                row.push(Cell::blank());
+                continue;
+            };
+            let [r, g, b] = [mean.0, mean.1, mean.2].map(linear_to_srgb);
+            let luma = opts.luma.luma(r, g, b);
+
+            let cell_y = (y / 2) as usize;
+            let level = fill(luma).clamp(0.0, 1.0) * top + error_diffusion[cell_y][x as usize];
+            let index = level.round().clamp(0.0, top) as usize;
+
+            if opts.dither == Dither::FloydSteinberg {
+                let error = level - index as f32;
+                dither::diffuse(
+                    dither::FLOYD_STEINBERG,
+                    (x as i32, cell_y as i32),
+                    (width as i32, rows as i32),
+                    opts.edge_handling,
+                    |nx, ny, factor| error_diffusion[ny][nx] += error * factor,
+                );
+            }
+            row.push(Cell { glyph: ramp[index], ..Cell::blank() });
+        }
+        grid.push(row);
+    }
+    grid
+}
@@ -0,0 +1,16 @@
+use clap::Parser;
+use jiv2::Mode;
+use std::process::ExitCode;
+
+/// A CLI tool to display images in the terminal using Braille characters
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(flatten)]
+    args: jiv2::cli::Args,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    jiv2::cli::main(&cli.args, cli.args.fallback_mode(Mode::Braille))
+}
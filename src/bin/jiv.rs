@@ -0,0 +1,24 @@
+use clap::Parser;
+use jiv2::Mode;
+use std::process::ExitCode;
+
+/// A CLI tool to display images in the terminal using
+/// Unicode block, sextant and Braille characters.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Glyph set to render with [default: sextant, or the richest set the
+    /// terminal can likely show]
+    #[arg(short, long, value_enum)]
+    mode: Option<Mode>,
+
+    #[command(flatten)]
+    args: jiv2::cli::Args,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // An explicit --mode is used as is
+    let mode = cli.mode.unwrap_or_else(|| cli.args.fallback_mode(Mode::default()));
+    jiv2::cli::main(&cli.args, mode)
+}
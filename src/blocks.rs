@@ -0,0 +1,210 @@
+use crate::btc::linear_to_u8;
+use crate::cell::{Cell, Grid};
+use crate::color::{linear_to_oklab, oklab_distance, srgb_to_linear};
+use crate::{Mode, RenderOptions, Renderer};
+use image::DynamicImage;
+
+/// Pixels sampled across and down each cell: one per eighth.
+const EIGHTHS: u32 = 8;
+
+/// Lower blocks from one eighth of the cell tall up to the full block.
+const LOWER: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Left blocks from one eighth of the cell wide up to seven.
+const LEFT: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// The shades, with the share of the cell each inks.
+const SHADES: [(char, f32); 3] = [('░', 0.25), ('▒', 0.5), ('▓', 0.75)];
+
+/// How much less error (summed squared Oklab distance) a glyph must have
+/// than the best so far to replace it, so rounding and faint grain don't
+/// break up flat cells.
+const MARGIN: f32 = 1e-3;
+
+type Linear = (f32, f32, f32);
+
+/// One sampled pixel of a cell: where it sits, in eighths, and its color.
+struct Sample {
+    x: u32,
+    y: u32,
+    linear: Linear,
+    oklab: (f32, f32, f32),
+    luma: f32,
+    alpha: f32,
+}
+
+/// Eighth-block bars and shades, as a [`Renderer`]; see [`render`].
+pub struct LegacyBlocks;
+
+impl Renderer for LegacyBlocks {
+    fn cell_size(&self) -> (u32, u32) {
+        // Bars place their edge to an eighth, but each cell has just
+        // the one edge
+        (1, 1)
+    }
+
+    fn render(&self, img: &DynamicImage, opts: &RenderOptions) -> Grid {
+        render(img, opts)
+    }
+}
+
+/// Renders an image with the block elements: each cell is an eighth-block
+/// bar, filling it from the bottom or the left, or a shade, whichever
+/// draws the cell's 8x8 pixels best. Bars follow a cell's brightness
+/// profile down or across it, which suits sparklines and waveforms; shades
+/// stand in for texture no bar lines up with.
+pub fn render(img: &DynamicImage, opts: &RenderOptions) -> Grid {
+    let mut resized = crate::fit_image_scaled(img, opts, Mode::LegacyBlocks, EIGHTHS);
+
+    if opts.sharpen_after_resize() {
+        resized = image::imageops::filter3x3(&resized, &crate::SHARPEN);
+    }
+
+    let (width, height) = resized.dimensions();
+    let mut grid = Grid::new();
+
+    for y in (0..height).step_by(EIGHTHS as usize) {
+        let mut row = Vec::new();
+
+        for x in (0..width).step_by(EIGHTHS as usize) {
+            if crate::key::keyed_cell(&resized, opts, (x, y), (EIGHTHS, EIGHTHS)) {
+                row.push(Cell::blank());
+                continue;
+            }
+
+            // The last row and column of cells can be cut short
+            let mut samples = Vec::new();
+            for py in y..(y + EIGHTHS).min(height) {
+                for px in x..(x + EIGHTHS).min(width) {
+                    let p = resized.get_pixel(px, py);
+                    let (r, g, b) = (p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0);
+                    let linear = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+                    samples.push(Sample {
+                        x: px - x,
+                        y: py - y,
+                        linear,
+                        oklab: linear_to_oklab(linear.0, linear.1, linear.2),
+                        luma: opts.posterize(opts.luma.luma(r, g, b)),
+                        alpha: p[3] as f32 / 255.0,
+                    });
+                }
+            }
+
+            let (glyph, fg, bg) = best_glyph(&samples, opts);
+            row.push(Cell {
+                glyph,
+                fg: Some(opts.cell_color(linear_to_u8(fg))),
+                bg: Some(opts.cell_color(linear_to_u8(bg))),
+                bold: false,
+            });
+        }
+        grid.push(row);
+    }
+    grid
+}
+
+/// The glyph, foreground and background that draw `samples` best. A bar
+/// splits the cell into two flat areas, each in its pixels' average color.
+/// A shade splits the pixels at their mean luma instead, wherever they
+/// are, and inks the share nearest the lit one's: the brighter group, or
+/// the darker with `opts.invert_diffusion`. The full block goes
+/// first, so flat cells stay solid.
+fn best_glyph(samples: &[Sample], opts: &RenderOptions) -> (char, Linear, Linear) {
+    let bars = LOWER.iter().enumerate().rev().map(|(i, &glyph)| (glyph, EIGHTHS - 1 - i as u32, 0));
+    let bars = bars.chain(LEFT.iter().enumerate().map(|(i, &glyph)| (glyph, 0, i as u32 + 1)));
+
+    let mut best = None;
+    for (glyph, top, right) in bars {
+        // Lit below row `top`, and left of column `right` for the left blocks
+        let lit = |s: &Sample| s.y >= top && (right == 0 || s.x < right);
+        let (fg, bg) = group_colors(samples, lit, opts);
+        let paint = |s: &Sample| if lit(s) { fg } else { bg };
+        let error = pattern_error(samples, |s| oklab_distance(s.oklab, to_oklab(paint(s))), paint);
+        if best.as_ref().is_none_or(|&(_, _, _, e)| error < e - MARGIN) {
+            best = Some((glyph, fg, bg, error));
+        }
+    }
+
+    let mean_luma = samples.iter().map(|s| s.luma).sum::<f32>() / samples.len().max(1) as f32;
+    let lit = |s: &Sample| (s.luma >= mean_luma) != opts.invert_diffusion;
+    let lit_share = samples.iter().filter(|s| lit(s)).count() as f32 / samples.len().max(1) as f32;
+    if 0.0 < lit_share && lit_share < 1.0 {
+        let (fg, bg) = group_colors(samples, lit, opts);
+        let (glyph, ink) = SHADES
+            .into_iter()
+            .min_by(|a, b| (a.1 - lit_share).abs().total_cmp(&(b.1 - lit_share).abs()))
+            .unwrap();
+        let (fg_oklab, bg_oklab) = (to_oklab(fg), to_oklab(bg));
+        let mixed = mix(fg, bg, ink);
+        // Up close every pixel is near one of the two colors; from afar
+        // the whole cell is their mix
+        let error = pattern_error(
+            samples,
+            |s| oklab_distance(s.oklab, fg_oklab).min(oklab_distance(s.oklab, bg_oklab)),
+            |_| mixed,
+        );
+        if best.as_ref().is_none_or(|&(_, _, _, e)| error < e - MARGIN) {
+            best = Some((glyph, fg, bg, error));
+        }
+    }
+
+    let (glyph, fg, bg, _) = best.unwrap_or((' ', (0.0, 0.0, 0.0), (0.0, 0.0, 0.0), 0.0));
+    (glyph, fg, bg)
+}
+
+/// The average colors of the `lit` samples and of the rest. An empty group,
+/// or a transparent one when weighting by alpha, borrows the other's color,
+/// so it is invisible either way.
+fn group_colors(samples: &[Sample], lit: impl Fn(&Sample) -> bool, opts: &RenderOptions) -> (Linear, Linear) {
+    let (fg, bg): (Vec<_>, Vec<_>) = samples.iter().partition(|s| lit(s));
+    let average = |group: Vec<&Sample>| {
+        let colors: Vec<_> = group.iter().map(|s| s.linear).collect();
+        let alphas: Vec<_> = group.iter().map(|s| s.alpha).collect();
+        (!colors.is_empty()).then(|| opts.cell_mean(&colors, &alphas, colors.len() as f32)).flatten()
+    };
+    let (fg, bg) = (average(fg), average(bg));
+    let fg = fg.or(bg).unwrap_or((0.0, 0.0, 0.0));
+    (fg, bg.unwrap_or(fg))
+}
+
+/// How far a candidate glyph is from `samples`, both up close (`near`, per
+/// pixel) and from a little way off: the colors it `paint`s, averaged over
+/// each 2x2 block of pixels, against the samples' own averages.
+fn pattern_error(samples: &[Sample], near: impl Fn(&Sample) -> f32, paint: impl Fn(&Sample) -> Linear) -> f32 {
+    let blocks = (EIGHTHS / 2) as usize;
+    let mut wanted = vec![((0.0, 0.0, 0.0), 0.0); blocks * blocks];
+    let mut painted = vec![(0.0, 0.0, 0.0); blocks * blocks];
+    let mut error = 0.0;
+    for s in samples {
+        error += near(s);
+        let block = (s.y / 2) as usize * blocks + (s.x / 2) as usize;
+        let (color, count) = &mut wanted[block];
+        *color = add(*color, s.linear);
+        *count += 1.0;
+        painted[block] = add(painted[block], paint(s));
+    }
+    for ((wanted, count), painted) in wanted.into_iter().zip(painted) {
+        if count > 0.0 {
+            let (wanted, painted) = (scale(wanted, 1.0 / count), scale(painted, 1.0 / count));
+            error += count * oklab_distance(to_oklab(wanted), to_oklab(painted));
+        }
+    }
+    error
+}
+
+fn to_oklab(c: Linear) -> (f32, f32, f32) {
+    linear_to_oklab(c.0, c.1, c.2)
+}
+
+fn add(a: Linear, b: Linear) -> Linear {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn scale(c: Linear, by: f32) -> Linear {
+    (c.0 * by, c.1 * by, c.2 * by)
+}
+
+/// The color a glyph inking `coverage` of the cell appears as from a distance.
+fn mix(fg: Linear, bg: Linear, coverage: f32) -> Linear {
+    add(scale(fg, coverage), scale(bg, 1.0 - coverage))
+}
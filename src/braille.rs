@@ -1,110 +1,152 @@
-use clap::Parser;
-use crossterm::terminal;
-use image::{imageops::FilterType, GenericImageView};
-use std::path::PathBuf;
-
-/// A CLI tool to display images in the terminal using Braille characters
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// Paths to the input images
-    #[arg(value_name = "FILES", num_args = 1..)]
-    paths: Vec<PathBuf>,
-
-    /// Enable edge detection enhancement
-    #[arg(short, long)]
-    edges: bool,
-
-    // optional width parameter
-    // Specify the width of the output in characters
-    // If not provided, the terminal width will be used.
-    // this value is in characters, not pixels
-    #[arg(short, long)]
-    width: Option<u32>,
+use crate::cell::{Cell, Grid};
+use crate::color::{linear_to_srgb, srgb_to_linear, LumaWeights};
+use crate::{Mode, RenderOptions, Renderer};
+use image::{DynamicImage, GrayImage, Luma};
+use std::str::FromStr;
+
+/// Where each dot of a braille cell sits, as `(dx, dy, bit)`: a glyph is
+/// U+2800 plus the OR of its dots' bits. Unicode numbers the dots down the
+/// left column (1, 2, 3) and then the right (4, 5, 6), with the bottom row
+/// (7 left, 8 right) added later, and dot n is bit n - 1.
+pub const BRAILLE_COORDS: [(u32, u32, u32); 8] = [
+    (0, 0, 0x01), (0, 1, 0x02), (0, 2, 0x04), (1, 0, 0x08),
+    (1, 1, 0x10), (1, 2, 0x20), (0, 3, 0x40), (1, 3, 0x80),
+];
+
+/// The image channel the braille dots are dithered from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Channel {
+    /// Perceived brightness
+    #[default]
+    Luma,
+    /// Red only
+    R,
+    /// Green only
+    G,
+    /// Blue only
+    B,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+/// How the level a braille dot turns on at is chosen. Levels are on the
+/// 0-255 scale the dots are dithered on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Threshold {
+    /// The same level everywhere
+    Fixed(f32),
+    /// Otsu's threshold, computed from the whole image's histogram
+    Auto,
+    /// Each cell's own mean, plus an offset
+    Adaptive(f32),
+}
 
-    if args.paths.is_empty() {
-        eprintln!("No input files specified.");
-        return Ok(());
+impl Default for Threshold {
+    fn default() -> Self {
+        Threshold::Fixed(128.0)
     }
+}
 
-    for path in &args.paths {
-        // Print filename header if there are multiple files
-        if args.paths.len() > 1 {
-            println!("\n--- {} ---", path.display());
-        }
+impl FromStr for Threshold {
+    type Err = String;
 
-        match render_image(path, &args) {
-            Ok(_) => {},
-            Err(e) => eprintln!("Error processing {}: {}", path.display(), e),
+    /// Parses a level from 0 to 255, or `auto` for Otsu's threshold.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Threshold::Auto);
         }
+        let level: f32 = s.parse().map_err(|_| format!("expected a level or `auto`: {}", s))?;
+        if !(0.0..=255.0).contains(&level) {
+            return Err(format!("threshold must be between 0 and 255: {}", s));
+        }
+        Ok(Threshold::Fixed(level))
     }
-
-    Ok(())
 }
 
-fn render_image(path: &PathBuf, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
-    // 1. Load the image
-    let img = image::open(path).map_err(|e| format!("Failed to open image: {}", e))?;
+/// Braille dots, as a [`Renderer`]; see [`render`].
+pub struct Braille;
 
-    // 2. Get terminal size
-    let (term_w, term_h) = terminal::size().unwrap_or((80, 24));
-
-    // 3. Calculate target size
-    // Braille characters are 2 pixels wide and 4 pixels tall.
-    let mut target_width = (term_w as u32) * 2;
-    // We subtract a bit from height to ensure it fits with prompt/headers
-    let target_height = ((term_h as u32).saturating_sub(2)) * 4;
+impl Renderer for Braille {
+    fn cell_size(&self) -> (u32, u32) {
+        (2, 4)
+    }
 
-    if args.width.is_some() {
-        target_width = args.width.unwrap() * 2;
+    fn render(&self, img: &DynamicImage, opts: &RenderOptions) -> Grid {
+        render(img, opts)
     }
+}
 
-    // 4. Resize image preserving aspect ratio
-    let resized = img.resize(target_width, target_height, FilterType::Lanczos3);
+/// Renders an image with Braille characters, dithering the dots from
+/// `opts.channel` (luma by default) and coloring each cell with its boosted
+/// average color, or `opts.tint` for a monochrome look. With
+/// `opts.supersample` each dot is dithered as a block of pixels and shows by
+/// how much of the block came out on, and with `opts.antialias_edges` the
+/// dots of cells along edges are dimmed by how sparse they are. With
+/// `opts.invert_diffusion` the dots go on the dark pixels instead.
+pub fn render(img: &DynamicImage, opts: &RenderOptions) -> Grid {
+    // 1-3. Resize image preserving aspect ratio to fit the terminal
+    // Braille characters are 2 pixels wide and 4 pixels tall, or 2N by 4N
+    // when supersampling: each dot is then an NxN block.
+    let n = opts.supersample.max(1);
+    let resized = crate::fit_image_scaled(img, opts, Mode::Braille, n);
+
+    // 4. Convert to Grayscale (Luma8) for structure, or pick out a single channel
+    let mut gray_image = match opts.channel {
+        Channel::Luma => luma_image(&resized, opts.luma),
+        Channel::R => channel_image(&resized, 0),
+        Channel::G => channel_image(&resized, 1),
+        Channel::B => channel_image(&resized, 2),
+    };
+
+    // 5. Optional: Edge Detection
+    if opts.sharpen_after_resize() {
+        gray_image = image::imageops::filter3x3(&gray_image, &crate::SHARPEN);
+    }
 
-    // 5. Convert to Grayscale (Luma8) for structure
-    let mut gray_image = resized.to_luma8();
+    if opts.posterize.is_some() {
+        for p in gray_image.pixels_mut() {
+            p[0] = (opts.posterize(p[0] as f32 / 255.0) * 255.0).round() as u8;
+        }
+    }
 
-    // 6. Optional: Edge Detection
-    if args.edges {
-        let kernel = [
-            0.0, -1.0, 0.0,
-            -1.0, 5.0, -1.0,
-            0.0, -1.0, 0.0
-        ];
-        gray_image = image::imageops::filter3x3(&gray_image, &kernel);
+    // Dots then stand for dark pixels; the colors are left alone
+    if opts.invert_diffusion {
+        image::imageops::invert(&mut gray_image);
     }
 
-    // 7. Render Loop
+    let otsu_level = (opts.threshold == Threshold::Auto).then(|| otsu_threshold(&gray_image));
+
+    // 6. Render Loop
     let (width, height) = gray_image.dimensions();
 
     let mut error_diffusion : Vec<Vec<f32>> = vec![vec![0.0; width as usize]; height as usize];
+    let (dots_wide, dots_high) = (width.div_ceil(n), height.div_ceil(n));
+    let mut coverage_error = vec![vec![0.0f32; dots_wide as usize]; dots_high as usize];
+    let mut grid = Grid::new();
+
+    // Iterate through the image in chunks of 2x4 dots (Width x Height of a Braille char)
+    for y in (0..height).step_by(4 * n as usize) {
+        let mut row = Vec::new();
+
+        for x in (0..width).step_by(2 * n as usize) {
+            if crate::key::keyed_cell(&resized, opts, (x, y), (2 * n, 4 * n)) {
+                row.push(Cell::blank());
+                continue;
+            }
+
+            // Colors to average with their alphas, and the luma range
+            let mut colors = Vec::with_capacity((8 * n * n) as usize);
+            let mut alphas = Vec::with_capacity((8 * n * n) as usize);
 
-    // Iterate through the image in chunks of 2x4 (Width x Height of a Braille char)
-    for y in (0..height).step_by(4) {
-        let mut line = String::new();
-        
-        for x in (0..width).step_by(2) {
-            // Variables to track color averages and luma range
-            let mut r_total: f32 = 0.0;
-            let mut g_total: f32 = 0.0;
-            let mut b_total: f32 = 0.0;
-            
             let mut min_luma: u8 = 255;
             let mut max_luma: u8 = 0;
 
-            for dy in 0..4 {
-                for dx in 0..2 {
+            for dy in 0..4 * n {
+                for dx in 0..2 * n {
                     if x + dx < width && y + dy < height {
                         // Color accumulation
                         let pixel = resized.get_pixel(x + dx, y + dy);
-                        r_total += srgb_to_linear(pixel[0] as f32 / 255.0);
-                        g_total += srgb_to_linear(pixel[1] as f32 / 255.0);
-                        b_total += srgb_to_linear(pixel[2] as f32 / 255.0);
+                        let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| srgb_to_linear(c as f32 / 255.0));
+                        colors.push((r, g, b));
+                        alphas.push(pixel[3] as f32 / 255.0);
 
                         // Luma min/max tracking from the grayscale image
                         let luma_pixel = gray_image.get_pixel(x + dx, y + dy)[0];
@@ -113,26 +155,23 @@ fn render_image(path: &PathBuf, args: &Args) -> Result<(), Box<dyn std::error::E
                     }
                 }
             }
-            
-            let count = 8.0; // 2*4
-            let r_avg= r_total / count;
-            let g_avg = g_total / count;
-            let b_avg = b_total / count;
 
-            // Check if the block is relatively flat (solid color or smooth gradient)
-            let diff = max_luma.abs_diff(min_luma);
-            let is_flat = diff < 20; // Threshold: adjusted to 30 for smoothness
+            let count = (8 * n * n) as f32; // 2*4 dots of NxN
+            let (r_avg, g_avg, b_avg) = opts.cell_mean(&colors, &alphas, count).unwrap_or_default();
 
             let (r_final, g_final, b_final);
-            let mut byte_mask: u8 = 0;
+            let mut byte_mask: u32 = 0;
 
             let diff = max_luma.abs_diff(min_luma) as f32 / 255.0;
             let bleh = 0.8 * (1.0 - diff) + 0.5 * diff;
             //let bleh = 0.5 * (1.0 - diff) + 0.5 * diff;
-            let blah = 1.0-bleh;
+            // The curve luma goes through before the dots are dithered: by
+            // default the complement of the color boost, so coverage times
+            // dot brightness comes back to the cell's tone
+            let blah = opts.luma_gamma.unwrap_or(1.0 - bleh);
+
 
 
-            
                 // RENDER DITHERED BLOCK (Original Logic)
 
                 // 1. Color: Apply sqrt boost for sparse dots
@@ -142,42 +181,95 @@ fn render_image(path: &PathBuf, args: &Args) -> Result<(), Box<dyn std::error::E
                 b_final = linear_to_srgb(b);
 
                 // 2. Shape: Calculate Braille dots via error diffusion
-                let coords = [
-                    (0, 0, 0x01), (0, 1, 0x02), (0, 2, 0x04), (1, 0, 0x08),
-                    (1, 1, 0x10), (1, 2, 0x20), (0, 3, 0x40), (1, 3, 0x80),
-                ];
+                let coords = BRAILLE_COORDS;
+                // The pixels of each dot that are inside the image, in order
+                let dot_pixels = |dot_x: u32, dot_y: u32| {
+                    (0..n * n)
+                        .map(move |i| (x + dot_x * n + i % n, y + dot_y * n + i / n))
+                        .filter(|&(px, py)| px < width && py < height)
+                };
+
+                let dot_level = |v: u8| srgb_to_linear(v as f32 / 255.0).powf(blah) * 255.0;
+                let dot_luma = |px: u32, py: u32| dot_level(gray_image.get_pixel(px, py)[0]);
+
+                let threshold = match opts.threshold {
+                    Threshold::Fixed(level) => level,
+                    // The Otsu level is a gray value, so it goes through the same curve as the pixels
+                    Threshold::Auto => dot_level(otsu_level.unwrap_or(128)),
+                    Threshold::Adaptive(offset) => {
+                        let inside: Vec<f32> = coords.iter()
+                            .flat_map(|&(dx, dy, _)| dot_pixels(dx, dy))
+                            .map(|(px, py)| dot_luma(px, py))
+                            .collect();
+                        inside.iter().sum::<f32>() / inside.len() as f32 + offset
+                    }
+                };
 
                 for (dx, dy, bit) in coords {
-                    if x + dx < width && y + dy < height {
-                        let pixel = gray_image.get_pixel(x + dx, y + dy);
+                    let (mut lit, mut total) = (0, 0);
+                    for (px, py) in dot_pixels(dx, dy) {
+                        // Ordered dithering spreads the threshold over a Bayer
+                        // tile centered on it, and diffuses nothing
+                        if opts.ordered {
+                            let level = threshold + (crate::dither::bayer(px, py) - 0.5) * 255.0;
+                            lit += (dot_luma(px, py) > level) as u32;
+                            total += 1;
+                            continue;
+                        }
+
                         // Apply error diffusion from previous pixels
-                        let luma = srgb_to_linear(pixel.0[0] as f32 / 255.0).powf(blah) * 255.0 + error_diffusion[(y + dy) as usize][(x + dx) as usize];
+                        let luma = dot_luma(px, py) + error_diffusion[py as usize][px as usize];
 
-                        let is_on = luma > 128.0;
+                        let is_on = luma > threshold;
 
                         if is_on {
-                            byte_mask |= bit;
+                            lit += 1;
                         }
-                        
+                        total += 1;
+
                         // Calculate Error
                         let error_value = luma as i16 - if is_on { 255 } else { 0 };
-                        
+
                         // Distribute error to neighboring pixels (Stucki kernel)
-                        let diffusion_coords = [
-                            (1, 0, 8.0 / 42.0), (2, 0, 4.0 / 42.0),
-                            (-2, 1, 2.0 / 42.0), (-1, 1, 4.0 / 42.0), (0, 1, 8.0 / 42.0), (1, 1, 4.0 / 42.0), (2, 1, 2.0 / 42.0),
-                            (-2, 2, 1.0 / 42.0), (-1, 2, 2.0 / 42.0), (0, 2, 4.0 / 42.0), (1, 2, 2.0 / 42.0), (2, 2, 1.0 / 42.0),
-                        ];
-                        for (dx_e, dy_e, factor) in diffusion_coords {
-                            let nx = x as i32 + dx as i32 + dx_e;
-                            let ny = y as i32 + dy as i32 + dy_e;
-                            if nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32 {
-                                error_diffusion[ny as usize][nx as usize] += error_value as f32 * factor;
-                            }
+                        crate::dither::diffuse(
+                            crate::dither::STUCKI,
+                            (px as i32, py as i32),
+                            (width as i32, height as i32),
+                            opts.edge_handling,
+                            |nx, ny, factor| error_diffusion[ny][nx] += error_value as f32 * factor,
+                        );
+                    }
+
+                    if total == 0 {
+                        continue;
+                    }
+                    // The dot shows by how much of its block dithered on; the
+                    // rounding is diffused across dots too, so tone is kept.
+                    // One pixel per dot always rounds exactly.
+                    let (dot_x, dot_y) = (x / n + dx, y / n + dy);
+                    if opts.ordered {
+                        if lit as f32 / total as f32 > crate::dither::bayer(dot_x, dot_y) {
+                            byte_mask |= bit;
                         }
+                        continue;
+                    }
+                    let coverage = lit as f32 / total as f32 + coverage_error[dot_y as usize][dot_x as usize];
+                    let is_on = coverage > 0.5;
+                    if is_on {
+                        byte_mask |= bit;
+                    }
+                    let error_value = coverage - if is_on { 1.0 } else { 0.0 };
+                    if error_value != 0.0 {
+                        crate::dither::diffuse(
+                            crate::dither::STUCKI,
+                            (dot_x as i32, dot_y as i32),
+                            (dots_wide as i32, dots_high as i32),
+                            opts.edge_handling,
+                            |nx, ny, factor| coverage_error[ny][nx] += error_value * factor,
+                        );
                     }
                 }
-            
+
 
             // Convert calculated color to 0-255 sRGB for ANSI
             let r_ansi = (r_final.clamp(0.0, 1.0) * 255.0).round() as u8;
@@ -185,81 +277,107 @@ fn render_image(path: &PathBuf, args: &Args) -> Result<(), Box<dyn std::error::E
             let b_ansi = (b_final.clamp(0.0, 1.0) * 255.0).round() as u8;
 
             // Base Braille Unicode char is U+2800
-            let braille_char = char::from_u32(0x2800 + byte_mask as u32).unwrap_or(' ');
-            
-            // ANSI escape: bold + truecolor foreground + black background
-            use std::fmt::Write as _;
-            write!(line, "\x1b[1;38;2;{};{};{};48;2;0;0;0m{}\x1b[0m", r_ansi, g_ansi, b_ansi, braille_char).unwrap();
+            let braille_char = char::from_u32(0x2800 + byte_mask);
+            debug_assert!(braille_char.is_some(), "mask {:#x} is outside the braille block", byte_mask);
+            let braille_char = braille_char.unwrap_or(' ');
+
+            // Bold (to intensify the color) + truecolor (or tint)
+            // foreground + black background
+            row.push(Cell {
+                glyph: braille_char,
+                fg: Some(opts.tint.unwrap_or_else(|| opts.cell_color((r_ansi, g_ansi, b_ansi)))),
+                bg: Some((0, 0, 0)),
+                bold: !opts.no_bold,
+            });
         }
-        println!("{}", line);
+        grid.push(row);
+    }
+    if opts.antialias_edges {
+        antialias_edges(&mut grid);
     }
-    Ok(())
+    grid
 }
 
-
-// --- Color space conversion functions ---
-fn srgb_to_linear(c: f32) -> f32 {
-    if c <= 0.04045 {
-        c / 12.92
-    } else {
-        ((c + 0.055) / 1.055).powf(2.4)
+/// Dots per braille cell that are on; none for anything else, such as the
+/// blank cells keying leaves.
+fn coverage(cell: &Cell) -> u32 {
+    match cell.glyph {
+        '\u{2800}'..='\u{28FF}' => (cell.glyph as u32 - 0x2800).count_ones(),
+        _ => 0,
     }
 }
 
-fn linear_to_srgb(c: f32) -> f32 {
-    if c <= 0.0031308 {
-        12.92 * c
-    } else {
-        1.055 * c.powf(1.0 / 2.4) - 0.055
+/// Softens jagged edges: cells whose dot count differs from a neighbor's by
+/// half a cell or more have their foreground blended toward the background
+/// in linear light, by the share of their dots that are off.
+fn antialias_edges(grid: &mut Grid) {
+    let coverages: Vec<Vec<u32>> = grid.iter().map(|row| row.iter().map(coverage).collect()).collect();
+    let at = |x: usize, y: usize| coverages.get(y).and_then(|row| row.get(x)).copied();
+
+    for (y, row) in grid.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            let dots = coverages[y][x];
+            let neighbors = [at(x.wrapping_sub(1), y), at(x + 1, y), at(x, y.wrapping_sub(1)), at(x, y + 1)];
+            let on_edge = neighbors.into_iter().flatten().any(|other| other.abs_diff(dots) >= 4);
+            let (Some(fg), Some(bg)) = (cell.fg, cell.bg) else {
+                continue;
+            };
+            if !on_edge || dots == 0 {
+                continue;
+            }
+
+            let share = dots as f32 / 8.0;
+            let blend = |f: u8, b: u8| {
+                let (f, b) = (srgb_to_linear(f as f32 / 255.0), srgb_to_linear(b as f32 / 255.0));
+                (linear_to_srgb(b + (f - b) * share) * 255.0).round() as u8
+            };
+            cell.fg = Some((blend(fg.0, bg.0), blend(fg.1, bg.1), blend(fg.2, bg.2)));
+        }
     }
 }
 
-fn srgb_to_oklab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
-    // Convert sRGB [0,1] to linear
-    let r_lin = srgb_to_linear(r);
-    let g_lin = srgb_to_linear(g);
-    let b_lin = srgb_to_linear(b);
-    linear_to_oklab(r_lin, g_lin, b_lin)
+/// The luma of an RGBA image, taken with `weights`.
+fn luma_image(img: &image::RgbaImage, weights: LumaWeights) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        let p = img.get_pixel(x, y);
+        let luma = weights.luma(p[0] as f32, p[1] as f32, p[2] as f32);
+        Luma([luma.round().clamp(0.0, 255.0) as u8])
+    })
 }
 
-fn oklab_to_srgb(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
-    let (r_lin, g_lin, b_lin) = oklab_to_linear(l, a, b);
-    (
-        linear_to_srgb(r_lin),
-        linear_to_srgb(g_lin),
-        linear_to_srgb(b_lin),
-    )
+/// One channel of an RGBA image as grayscale.
+fn channel_image(img: &image::RgbaImage, channel: usize) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| Luma([img.get_pixel(x, y)[channel]]))
 }
 
-fn linear_to_oklab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
-    // 1. Linear RGB to LMS
-    let l = 0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b;
-    let m = 0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b;
-    let s = 0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b;
-    // 2. Cube root
-    let l_ = l.cbrt();
-    let m_ = m.cbrt();
-    let s_ = s.cbrt();
-    // 3. LMS to Oklab
-    let l = 0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_;
-    let a = 1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_;
-    let b = 0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_;
-    (l, a, b)
-}
+/// Otsu's method: the gray level that splits the histogram into the two
+/// classes with the greatest variance between them. Levels above it are "on".
+fn otsu_threshold(gray: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for p in gray.pixels() {
+        histogram[p[0] as usize] += 1;
+    }
+
+    let total = gray.pixels().len() as f64;
+    let sum_all: f64 = histogram.iter().enumerate().map(|(level, &n)| level as f64 * n as f64).sum();
+    let (mut weight_below, mut sum_below) = (0.0, 0.0);
+    let (mut best_level, mut best_variance) = (0, 0.0);
+
+    for (level, &n) in histogram.iter().enumerate() {
+        weight_below += n as f64;
+        sum_below += level as f64 * n as f64;
+        let weight_above = total - weight_below;
+        if weight_below == 0.0 || weight_above == 0.0 {
+            continue;
+        }
 
-fn oklab_to_linear(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
-    // 1. Oklab to LMS
-    let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
-    let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
-    let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
-    // 2. Cube
-    let l = l_.powi(3);
-    let m = m_.powi(3);
-    let s = s_.powi(3);
-    // 3. LMS to linear RGB
-    let r = 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s;
-    let g = -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s;
-    let b = -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s;
-    (r, g, b)
+        let mean_below = sum_below / weight_below;
+        let mean_above = (sum_all - sum_below) / weight_above;
+        let variance = weight_below * weight_above * (mean_below - mean_above).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best_level = level;
+        }
+    }
+    best_level as u8
 }
-// --- End color space conversion functions ---
\ No newline at end of file
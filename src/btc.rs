@@ -0,0 +1,287 @@
+use crate::cell::{Cell, Grid};
+use crate::color::{linear_to_oklab, linear_to_srgb, oklab_distance, srgb_to_linear};
+use crate::{GlyphMatch, Mode, RenderOptions};
+use image::DynamicImage;
+
+/// One sub-pixel of a cell, in sRGB with its luma.
+#[derive(Clone, Copy)]
+pub(crate) struct PixelData {
+    luma: f32,
+    r: f32,
+    g: f32,
+    b: f32,
+    alpha: f32,
+    mask_bit: u32,
+    /// Its bit in `Blocks::glyphs`, when there are blocks
+    block_bit: u32,
+}
+
+/// Solid glyphs for [`render_btc`] to draw flat cells with, where the fine
+/// glyphs are for detail: `bits` gives each of its `coords` the bit of the
+/// block it falls in, and `glyphs` maps the OR of the lit bits to a
+/// character. Cells whose luma spreads less than `spread` (as a standard
+/// deviation) get blocks.
+pub(crate) struct Blocks<'a> {
+    pub bits: &'a [u32],
+    pub glyphs: &'a [char],
+    pub spread: f32,
+}
+
+/// Renders an image with Block Truncation Coding (BTC) for true-color
+/// structure: each cell's sub-pixels are split at their average luma into a
+/// foreground (lit) and background group, each drawn in its average color.
+///
+/// `coords` lists the sub-pixels of a `mode` cell as `(dx, dy, mask_bit)`, and
+/// `glyphs` maps the OR of the lit bits to a character. `coverage` is the
+/// fraction of a lit sub-pixel the glyph actually inks: 1.0 for solid blocks,
+/// less for dots. Below 1.0 the foreground is boosted so it mixes with the
+/// background to the target color, and whatever the boost can't reach is
+/// diffused to the neighboring sub-pixels. With `blocks`, flat cells are
+/// drawn with those instead, at full coverage.
+pub(crate) fn render_btc(
+    img: &DynamicImage,
+    opts: &RenderOptions,
+    mode: Mode,
+    coords: &[(u32, u32, u32)],
+    glyphs: &[char],
+    coverage: f32,
+    blocks: Option<&Blocks>,
+) -> Grid {
+    let mut resized = crate::fit_image(img, opts, mode);
+
+    if opts.sharpen_after_resize() {
+        resized = image::imageops::filter3x3(&resized, &crate::SHARPEN);
+    }
+
+    let (width, height) = resized.dimensions();
+    let (cell_w, cell_h) = mode.cell_size();
+    let mut error_diffusion = (coverage < 1.0)
+        .then(|| vec![vec![(0.0f32, 0.0f32, 0.0f32); width as usize]; height as usize]);
+    let mut grid = Grid::new();
+    // Serpentine rows run right to left every other time, with the kernel
+    // mirrored to match, so the error doesn't all drift one way
+    let mirrored: Vec<_> = crate::dither::STUCKI.iter().map(|&(dx, dy, weight)| (-dx, dy, weight)).collect();
+
+    for (row_index, y) in (0..height).step_by(cell_h as usize).enumerate() {
+        let mut row = Vec::new();
+        let reversed = opts.serpentine && error_diffusion.is_some() && row_index % 2 == 1;
+        let kernel = if reversed { &mirrored[..] } else { crate::dither::STUCKI };
+        let mut columns: Vec<u32> = (0..width).step_by(cell_w as usize).collect();
+        if reversed {
+            columns.reverse();
+        }
+
+        for x in columns {
+            if crate::key::keyed_cell(&resized, opts, (x, y), (cell_w, cell_h)) {
+                row.push(Cell::blank());
+                continue;
+            }
+
+            let mut pixels: Vec<PixelData> = Vec::with_capacity(coords.len());
+
+            // 1. Collect pixels with their luma
+            for (i, &(dx, dy, bit)) in coords.iter().enumerate() {
+                if x + dx < width && y + dy < height {
+                    let p = resized.get_pixel(x + dx, y + dy);
+                    let mut r = p[0] as f32 / 255.0;
+                    let mut g = p[1] as f32 / 255.0;
+                    let mut b = p[2] as f32 / 255.0;
+
+                    if let Some(error) = &error_diffusion {
+                        let (err_r, err_g, err_b) = error[(y + dy) as usize][(x + dx) as usize];
+                        r = linear_to_srgb(srgb_to_linear(r) + err_r);
+                        g = linear_to_srgb(srgb_to_linear(g) + err_g);
+                        b = linear_to_srgb(srgb_to_linear(b) + err_b);
+                    }
+
+                    let luma = opts.posterize(opts.luma.luma(r, g, b));
+                    let block_bit = blocks.map_or(0, |blocks| blocks.bits[i]);
+                    pixels.push(PixelData { luma, r, g, b, alpha: p[3] as f32 / 255.0, mask_bit: bit, block_bit });
+                }
+            }
+
+            if pixels.is_empty() {
+                row.push(Cell::blank());
+                continue;
+            }
+
+            // Cells flatter than the minimum contrast skip the split and
+            // are just their average color
+            if let Some(min_contrast) = opts.min_contrast {
+                let (low, high) = pixels.iter().fold((f32::MAX, f32::MIN), |(low, high), p| (low.min(p.luma), high.max(p.luma)));
+                if high - low < min_contrast {
+                    let mean = average_color_linear(&pixels, opts).unwrap_or((0.0, 0.0, 0.0));
+                    row.push(Cell { bg: Some(opts.cell_color(linear_to_u8(mean))), ..Cell::blank() });
+                    continue;
+                }
+            }
+
+            // Flat cells take the blocks, if there are any
+            let (glyphs, coverage) = match blocks {
+                Some(blocks) if luma_spread(&pixels) < blocks.spread => {
+                    for p in &mut pixels {
+                        p.mask_bit = p.block_bit;
+                    }
+                    (blocks.glyphs, 1.0)
+                }
+                _ => (glyphs, coverage),
+            };
+
+            // 2. Separate into Foreground (lit) and Background
+            let (mut fg_group, mut bg_group) = match opts.glyph_match {
+                GlyphMatch::Exhaustive if coverage == 1.0 => split_exhaustive(&pixels, opts),
+                _ => split_at_mean_luma(&pixels),
+            };
+            // Drawing the glyph in the other group's color complements its mask
+            if opts.invert_bg != opts.invert_diffusion {
+                std::mem::swap(&mut fg_group, &mut bg_group);
+            }
+            let char_mask = fg_group.iter().fold(0, |mask, p| mask | p.mask_bit);
+
+            // Edge case: if all pixels are identical, they all land in FG (>= threshold),
+            // giving a full glyph. An empty group (or a transparent one, weighting
+            // by alpha) borrows the other group's color, so it is invisible either way.
+            let average = |group: &[PixelData]| average_color_linear(group, opts);
+            let bg = average(&bg_group).unwrap_or(
+                average(&fg_group).unwrap_or((0.0, 0.0, 0.0))
+            );
+            let target_fg = average(&fg_group).unwrap_or(bg);
+
+            // 3. Calculate the "Un-mixed" foreground color
+            // Logic: Target_Color = coverage * FG_Dot + (1 - coverage) * BG
+            let fg = solve_dot_color(target_fg, bg, coverage);
+
+            // 4. Distribute what the clamped dot color misses (Stucki dithering)
+            if let Some(error) = &mut error_diffusion {
+                let mixed = mix(fg, bg, coverage);
+                let err = (target_fg.0 - mixed.0, target_fg.1 - mixed.1, target_fg.2 - mixed.2);
+
+                for &(dx, dy, _) in coords {
+                    if x + dx < width && y + dy < height {
+                        crate::dither::diffuse(
+                            kernel,
+                            ((x + dx) as i32, (y + dy) as i32),
+                            (width as i32, height as i32),
+                            opts.edge_handling,
+                            |nx, ny, factor| {
+                                let e = &mut error[ny][nx];
+                                e.0 += err.0 * factor;
+                                e.1 += err.1 * factor;
+                                e.2 += err.2 * factor;
+                            },
+                        );
+                    }
+                }
+            }
+
+            // Every mask `coords` can build must have a glyph; a gap is a bug
+            // in the bit assignments, not something to paper over with a space
+            debug_assert!(
+                (char_mask as usize) < glyphs.len(),
+                "mask {:#x} is past the {}-glyph table",
+                char_mask,
+                glyphs.len()
+            );
+            let glyph = glyphs.get(char_mask as usize).copied().unwrap_or(' ');
+
+            row.push(Cell {
+                glyph,
+                fg: Some(opts.cell_color(linear_to_u8(fg))),
+                bg: Some(opts.cell_color(linear_to_u8(bg))),
+                bold: false,
+            });
+        }
+        if reversed {
+            row.reverse();
+        }
+        grid.push(row);
+    }
+    grid
+}
+
+/// The standard deviation of a cell's sub-pixel lumas.
+fn luma_spread(pixels: &[PixelData]) -> f32 {
+    let count = pixels.len() as f32;
+    let mean = pixels.iter().map(|p| p.luma).sum::<f32>() / count;
+    (pixels.iter().map(|p| (p.luma - mean).powi(2)).sum::<f32>() / count).sqrt()
+}
+
+/// Splits a cell's sub-pixels at their average luma: those at or above it
+/// are foreground. Identical pixels all land in the foreground.
+fn split_at_mean_luma(pixels: &[PixelData]) -> (Vec<PixelData>, Vec<PixelData>) {
+    let luma_threshold = pixels.iter().map(|p| p.luma).sum::<f32>() / pixels.len() as f32;
+    pixels.iter().partition(|p| p.luma >= luma_threshold)
+}
+
+/// Tries every split of a cell's sub-pixels into foreground and background
+/// and keeps the one whose two average colors (averaged as `opts` says) are
+/// closest to the pixels, in Oklab.
+fn split_exhaustive(pixels: &[PixelData], opts: &RenderOptions) -> (Vec<PixelData>, Vec<PixelData>) {
+    let oklab = |(r, g, b): (f32, f32, f32)| linear_to_oklab(r, g, b);
+    let colors: Vec<_> = pixels
+        .iter()
+        .map(|p| oklab((srgb_to_linear(p.r), srgb_to_linear(p.g), srgb_to_linear(p.b))))
+        .collect();
+
+    let split = |lit: u32| -> (Vec<PixelData>, Vec<PixelData>) {
+        let mut groups = (Vec::new(), Vec::new());
+        for (i, &p) in pixels.iter().enumerate() {
+            if lit & (1 << i) != 0 { groups.0.push(p) } else { groups.1.push(p) }
+        }
+        groups
+    };
+    let error = |lit: u32| {
+        let (fg, bg) = split(lit);
+        let fg_mean = average_color_linear(&fg, opts).map(oklab);
+        let bg_mean = average_color_linear(&bg, opts).map(oklab);
+        colors.iter().enumerate().map(|(i, &c)| {
+            let mean = if lit & (1 << i) != 0 { fg_mean } else { bg_mean };
+            mean.map_or(0.0, |mean| oklab_distance(c, mean))
+        }).sum::<f32>()
+    };
+
+    // Starting from the full glyph keeps flat cells solid, as the luma split does
+    let all = (1u32 << pixels.len()) - 1;
+    let mut best = (all, error(all));
+    for lit in 1..all {
+        let e = error(lit);
+        if e < best.1 {
+            best = (lit, e);
+        }
+    }
+    split(best.0)
+}
+
+/// Returns the average linear RGB color for a group of pixels, averaged as
+/// `opts` says; `None` for no pixels, or only transparent ones when
+/// weighting by alpha
+fn average_color_linear(pixels: &[PixelData], opts: &RenderOptions) -> Option<(f32, f32, f32)> {
+    if pixels.is_empty() {
+        return None;
+    }
+    let colors: Vec<_> = pixels.iter().map(|p| (srgb_to_linear(p.r), srgb_to_linear(p.g), srgb_to_linear(p.b))).collect();
+    let alphas: Vec<_> = pixels.iter().map(|p| p.alpha).collect();
+    opts.cell_mean(&colors, &alphas, pixels.len() as f32)
+}
+
+/// Solves: coverage * Dot_Color + (1 - coverage) * BG = Target
+/// Clamps result between 0.0 and 1.0
+fn solve_dot_color(target: (f32, f32, f32), bg: (f32, f32, f32), coverage: f32) -> (f32, f32, f32) {
+    let solve = |t: f32, b: f32| ((t - (1.0 - coverage) * b) / coverage).clamp(0.0, 1.0);
+    (solve(target.0, bg.0), solve(target.1, bg.1), solve(target.2, bg.2))
+}
+
+/// The color a glyph with the given ink coverage appears as from a distance.
+fn mix(fg: (f32, f32, f32), bg: (f32, f32, f32), coverage: f32) -> (f32, f32, f32) {
+    (
+        coverage * fg.0 + (1.0 - coverage) * bg.0,
+        coverage * fg.1 + (1.0 - coverage) * bg.1,
+        coverage * fg.2 + (1.0 - coverage) * bg.2,
+    )
+}
+
+/// Converts a linear color to 0-255 sRGB for terminal codes.
+pub(crate) fn linear_to_u8(c: (f32, f32, f32)) -> (u8, u8, u8) {
+    let to_u8 = |v: f32| (linear_to_srgb(v).clamp(0.0, 1.0) * 255.0).round() as u8;
+    (to_u8(c.0), to_u8(c.1), to_u8(c.2))
+}
@@ -0,0 +1,370 @@
+use std::io::{self, Write};
+
+/// One character cell of rendered output: a glyph and its two colors (sRGB).
+/// A color of `None` leaves the terminal's default in place.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub glyph: char,
+    pub fg: Option<(u8, u8, u8)>,
+    pub bg: Option<(u8, u8, u8)>,
+    /// Emit the bold attribute with the foreground color
+    pub bold: bool,
+}
+
+impl Cell {
+    /// An unstyled space.
+    pub fn blank() -> Self {
+        Cell { glyph: ' ', fg: None, bg: None, bold: false }
+    }
+
+    /// Whether the cell shows nothing but the terminal's default background.
+    pub fn is_blank(&self) -> bool {
+        matches!(self.glyph, ' ' | '\u{2800}') && self.bg.is_none()
+    }
+}
+
+/// Rows of cells, top to bottom.
+pub type Grid = Vec<Vec<Cell>>;
+
+/// Where and how [`write_ansi_with`] lays rows out on the terminal.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AnsiOptions {
+    /// Overwrite this many lines above the cursor (e.g. the previous frame
+    /// of an animation), clearing each row's tail instead of the screen, so
+    /// nothing flashes between frames
+    pub redraw_rows: usize,
+    /// Put row `i` at the 1-based screen position (`row + i`, `col`) instead
+    /// of at the cursor; no newlines are written, so nothing outside the
+    /// grid's rectangle is touched
+    pub at: Option<(u32, u32)>,
+    /// Drop blank cells on the default background from the end of each row,
+    /// clearing to the end of the line instead
+    pub trim_trailing: bool,
+    /// Emit the rows bottom to top, for consumers that read lines that way
+    pub bottom_up: bool,
+    /// How glyphs outside ASCII are written
+    pub encoding: OutputEncoding,
+    /// End each row with a NUL byte instead of a newline, so a reader can
+    /// split the rows without guessing at line endings
+    pub nul_terminated: bool,
+}
+
+/// How [`write_ansi_with`] writes glyphs outside ASCII.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputEncoding {
+    /// As UTF-8
+    #[default]
+    Utf8,
+    /// As `\uXXXX` escapes of their UTF-16 code units (surrogate pairs past
+    /// U+FFFF, as in JSON), so the output is pure ASCII
+    AsciiEscape,
+}
+
+/// Streams a grid as truecolor ANSI, one line per row.
+///
+/// Each styled cell sets its attributes explicitly; attributes a cell drops
+/// are reset to the default, and a row ending on a styled cell gets a full reset.
+pub fn write_ansi<W: Write>(grid: &Grid, w: W) -> io::Result<()> {
+    write_ansi_with(grid, &AnsiOptions::default(), w)
+}
+
+/// Lays grids out left to right, `gap` blank cells apart, each under its
+/// label. Short grids are padded with blank rows and ragged rows with blank
+/// cells, so every panel keeps its own column.
+pub fn side_by_side(panels: &[(String, Grid)], gap: usize) -> Grid {
+    let widths: Vec<usize> = panels
+        .iter()
+        .map(|(label, grid)| grid.iter().map(Vec::len).max().unwrap_or(0).max(label.chars().count()))
+        .collect();
+    let height = panels.iter().map(|(_, grid)| grid.len()).max().unwrap_or(0);
+
+    let mut labels = Vec::new();
+    let mut rows = vec![Vec::new(); height];
+    for (i, ((label, grid), &width)) in panels.iter().zip(&widths).enumerate() {
+        let spacing = if i > 0 { gap } else { 0 };
+        labels.extend(std::iter::repeat_n(Cell::blank(), spacing));
+        labels.extend(label.chars().map(|glyph| Cell { glyph, ..Cell::blank() }));
+        labels.extend(std::iter::repeat_n(Cell::blank(), width - label.chars().count()));
+
+        for (y, row) in rows.iter_mut().enumerate() {
+            let cells = grid.get(y).map_or(&[][..], Vec::as_slice);
+            row.extend(std::iter::repeat_n(Cell::blank(), spacing));
+            row.extend_from_slice(cells);
+            row.extend(std::iter::repeat_n(Cell::blank(), width - cells.len()));
+        }
+    }
+
+    let mut grid = vec![labels];
+    grid.extend(rows);
+    grid
+}
+
+/// How far --cell-debug shifts the background of every other cell, per
+/// channel.
+const CHECKER_SHIFT: u8 = 24;
+
+/// Shades the background of every other cell, checkerboard fashion, so the
+/// cell boundaries show: lighter on dark backgrounds, darker on light ones.
+/// Cells on the default background get a dark gray one.
+pub fn checker_cells(grid: &mut Grid) {
+    for (row, cells) in grid.iter_mut().enumerate() {
+        for cell in cells.iter_mut().skip(1 - row % 2).step_by(2) {
+            cell.bg = Some(match cell.bg {
+                Some((r, g, b)) if (r as u32 + g as u32 + b as u32) > 3 * 128 => {
+                    (r.saturating_sub(CHECKER_SHIFT), g.saturating_sub(CHECKER_SHIFT), b.saturating_sub(CHECKER_SHIFT))
+                }
+                Some((r, g, b)) => (r.saturating_add(CHECKER_SHIFT), g.saturating_add(CHECKER_SHIFT), b.saturating_add(CHECKER_SHIFT)),
+                None => (CHECKER_SHIFT, CHECKER_SHIFT, CHECKER_SHIFT),
+            });
+        }
+    }
+}
+
+/// Calls `visit(row, col, cell)` for every cell of a grid, top to bottom and
+/// left to right.
+pub fn visit_cells(grid: &Grid, mut visit: impl FnMut(usize, usize, &Cell)) {
+    for (row, cells) in grid.iter().enumerate() {
+        for (col, cell) in cells.iter().enumerate() {
+            visit(row, col, cell);
+        }
+    }
+}
+
+/// Streams a grid as truecolor ANSI, laid out as `opts` asks; see [`write_ansi`].
+pub fn write_ansi_with<W: Write>(grid: &Grid, opts: &AnsiOptions, mut w: W) -> io::Result<()> {
+    let redraw = opts.redraw_rows > 0 && opts.at.is_none();
+    if redraw {
+        write!(w, "\x1b[{}F", opts.redraw_rows)?;
+    }
+
+    let flipped: Grid;
+    let grid = if opts.bottom_up {
+        flipped = grid.iter().rev().cloned().collect();
+        &flipped
+    } else {
+        grid
+    };
+
+    let mut sink = AnsiSink { w, opts, redraw, end: 0, len: 0, prev: Cell::blank() };
+    let mut result = Ok(());
+    visit_cells(grid, |row, col, cell| {
+        if result.is_ok() {
+            result = sink.cell(grid, row, col, cell);
+        }
+    });
+    result?;
+    if !grid.is_empty() {
+        sink.end_row()?;
+    }
+    Ok(())
+}
+
+/// The state of [`write_ansi_with`] as it's fed one cell at a time.
+struct AnsiSink<'a, W> {
+    w: W,
+    opts: &'a AnsiOptions,
+    redraw: bool,
+    /// Cells of the row that are written; the rest are trimmed
+    end: usize,
+    /// Cells in the row
+    len: usize,
+    /// The last cell written, whose attributes are still in effect
+    prev: Cell,
+}
+
+impl<W: Write> AnsiSink<'_, W> {
+    fn cell(&mut self, grid: &Grid, row: usize, col: usize, cell: &Cell) -> io::Result<()> {
+        if col == 0 {
+            if row > 0 {
+                self.end_row()?;
+            }
+            self.start_row(&grid[row], row)?;
+        }
+        if col >= self.end {
+            return Ok(());
+        }
+
+        let mut params = Vec::with_capacity(3);
+        if cell.bold {
+            params.push("1".to_string());
+        } else if self.prev.bold {
+            params.push("22".to_string());
+        }
+        match cell.fg {
+            Some((r, g, b)) => params.push(format!("38;2;{};{};{}", r, g, b)),
+            None if self.prev.fg.is_some() => params.push("39".to_string()),
+            None => {}
+        }
+        match cell.bg {
+            Some((r, g, b)) => params.push(format!("48;2;{};{};{}", r, g, b)),
+            None if self.prev.bg.is_some() => params.push("49".to_string()),
+            None => {}
+        }
+
+        if !params.is_empty() {
+            write!(self.w, "\x1b[{}m", params.join(";"))?;
+        }
+        if self.opts.encoding == OutputEncoding::AsciiEscape && !cell.glyph.is_ascii() {
+            for unit in cell.glyph.encode_utf16(&mut [0; 2]) {
+                write!(self.w, "\\u{:04x}", unit)?;
+            }
+        } else {
+            write!(self.w, "{}", cell.glyph)?;
+        }
+        self.prev = *cell;
+        Ok(())
+    }
+
+    fn start_row(&mut self, cells: &[Cell], row: usize) -> io::Result<()> {
+        if let Some((top, left)) = self.opts.at {
+            write!(self.w, "\x1b[{};{}H", top + row as u32, left)?;
+        }
+
+        self.len = cells.len();
+        self.end = if self.opts.trim_trailing {
+            cells.iter().rposition(|cell| !cell.is_blank()).map_or(0, |last| last + 1)
+        } else {
+            cells.len()
+        };
+        self.prev = Cell::blank();
+        Ok(())
+    }
+
+    fn end_row(&mut self) -> io::Result<()> {
+        let prev = self.prev;
+        if prev.bold || prev.fg.is_some() || prev.bg.is_some() {
+            self.w.write_all(b"\x1b[0m")?;
+        }
+
+        // Positioned output leaves whatever is right of the grid alone
+        if self.redraw || (self.end < self.len && self.opts.at.is_none()) {
+            self.w.write_all(b"\x1b[K")?;
+        }
+        if self.opts.at.is_none() {
+            self.w.write_all(if self.opts.nul_terminated { b"\0" } else { b"\n" })?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a grid as truecolor ANSI, one line per row.
+pub fn to_ansi(grid: &Grid) -> String {
+    let mut buf = Vec::new();
+    write_ansi(grid, &mut buf).expect("writing to a Vec cannot fail");
+    String::from_utf8(buf).expect("ANSI output is valid UTF-8")
+}
+
+/// The SGR attributes [`optimize_ansi`] tracks.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Sgr {
+    fg: Option<(u8, u8, u8)>,
+    bg: Option<(u8, u8, u8)>,
+    bold: bool,
+}
+
+impl Sgr {
+    /// Applies the parameters of one SGR sequence; `None` if any of them is
+    /// one this doesn't model.
+    fn apply(mut self, params: &str) -> Option<Sgr> {
+        let params: Vec<Option<u8>> = params.split(';').map(|p| if p.is_empty() { Some(0) } else { p.parse().ok() }).collect();
+        let mut params = params.into_iter();
+        let rgb = |params: &mut std::vec::IntoIter<Option<u8>>| match params.next()?? {
+            2 => Some((params.next()??, params.next()??, params.next()??)),
+            _ => None,
+        };
+        while let Some(param) = params.next() {
+            match param? {
+                0 => self = Sgr::default(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                39 => self.fg = None,
+                49 => self.bg = None,
+                38 => self.fg = Some(rgb(&mut params)?),
+                48 => self.bg = Some(rgb(&mut params)?),
+                _ => return None,
+            }
+        }
+        Some(self)
+    }
+
+    /// The SGR sequence that takes the terminal from `from` to `self`,
+    /// setting only what changes, or everything after a reset when `from`
+    /// isn't known. Empty when nothing changes.
+    fn sequence_from(self, from: Option<Sgr>) -> String {
+        if from == Some(self) {
+            return String::new();
+        }
+        if self == Sgr::default() {
+            return "\x1b[0m".to_string();
+        }
+        let (mut params, from) = match from {
+            Some(from) => (Vec::new(), from),
+            None => (vec!["0".to_string()], Sgr::default()),
+        };
+        if self.bold != from.bold {
+            params.push(if self.bold { "1" } else { "22" }.to_string());
+        }
+        for (to, from, set, reset) in [(self.fg, from.fg, 38, 39), (self.bg, from.bg, 48, 49)] {
+            if to != from {
+                params.push(match to {
+                    Some((r, g, b)) => format!("{};2;{};{};{}", set, r, g, b),
+                    None => reset.to_string(),
+                });
+            }
+        }
+        format!("\x1b[{}m", params.join(";"))
+    }
+}
+
+/// Shrinks an ANSI stream without changing how it looks: each run of
+/// back-to-back SGR sequences becomes one holding only the attributes that
+/// actually change (so a reset straight before new colors, or a color set
+/// to what it already is, goes), and a run that changes nothing is dropped.
+/// Everything else, including SGR parameters this doesn't model, passes
+/// through untouched.
+pub fn optimize_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    // What the terminal shows so far; `None` after attributes this doesn't
+    // model, until a reset makes it known again
+    let mut shown = Some(Sgr::default());
+    // Where the current run of SGR sequences has taken it, not yet written
+    let mut pending: Option<Sgr> = None;
+
+    let mut rest = s;
+    while !rest.is_empty() {
+        if let Some(csi) = rest.strip_prefix("\x1b[") {
+            let end = csi.find(|c: char| !(c.is_ascii_digit() || c == ';')).unwrap_or(csi.len());
+            if csi[end..].starts_with('m') {
+                let params = &csi[..end];
+                let starts_with_reset = matches!(params.split(';').next(), Some("" | "0"));
+                let applied = pending
+                    .or(shown)
+                    .and_then(|state| state.apply(params))
+                    .or_else(|| if starts_with_reset { Sgr::default().apply(params) } else { None });
+                match applied {
+                    Some(state) => pending = Some(state),
+                    None => {
+                        if let Some(state) = pending.take() {
+                            out.push_str(&state.sequence_from(shown));
+                        }
+                        out.push_str(&rest[..end + 3]);
+                        shown = None;
+                    }
+                }
+                rest = &csi[end + 1..];
+                continue;
+            }
+        }
+        if let Some(state) = pending.take() {
+            out.push_str(&state.sequence_from(shown));
+            shown = Some(state);
+        }
+        let c = rest.chars().next().expect("rest is not empty");
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    if let Some(state) = pending {
+        out.push_str(&state.sequence_from(shown));
+    }
+    out
+}
@@ -0,0 +1,1418 @@
+use crate::braille::{Channel, Threshold};
+use crate::cell::{optimize_ansi, side_by_side, write_ansi_with, AnsiOptions, Grid, OutputEncoding};
+use crate::error::{JivError, Result};
+use crate::frames::{SortFiles, frames, list_images, load_frame};
+use crate::icon::IconEntry;
+#[cfg(feature = "http")]
+use crate::frames::frames_from_memory;
+use crate::color::{to_hex, Checker, ColorAverage, ColorDistance, LumaWeights};
+use crate::dither::{Dither, EdgeHandling};
+use crate::key::KeyColor;
+use crate::pager;
+use crate::palette;
+use crate::probe;
+use crate::raster::{self, GifWriter, GlyphAtlas};
+use crate::shrink::DecodeScale;
+use crate::stats::Stats;
+use crate::theme;
+use crate::tonemap::Tonemap;
+use crate::{terminal_size, Dimension, GlyphMatch, GlyphSupport, Mode, RenderOptions, SharpenOrder};
+use clap::ValueEnum;
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::terminal;
+use image::{Delay, DynamicImage, Frame};
+use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
+use std::cell::Cell;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How many colors --dominant-color clusters an image into.
+const DOMINANT_COLORS: usize = 5;
+
+/// How long each still image stays up when a slideshow is captured to GIF.
+const SLIDE_DELAY_MS: u32 = 1000;
+
+/// The modes --compare shows, left to right.
+const COMPARE_MODES: [Mode; 5] = [Mode::Braille, Mode::Octant, Mode::Sextant, Mode::Quadrant, Mode::HalfBlock];
+
+/// Blank columns between the panels of --compare.
+const COMPARE_GAP: usize = 2;
+
+/// How often the --progress spinner moves while an input is decoding.
+const PROGRESS_TICK: Duration = Duration::from_millis(100);
+
+/// How many rows tall --scroll lets a render get when no height is given.
+const SCROLL_MAX_ROWS: u32 = 4096;
+
+/// The shortest a frame delay scaled by --frame-delay-scale gets, so a
+/// tiny scale doesn't redraw as fast as it can.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(10);
+
+/// A region of the screen in cells, as given to --viewport.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+    /// Columns from the left edge of the screen
+    pub x: u32,
+    /// Rows from the top of the screen
+    pub y: u32,
+}
+
+// Arguments shared by every binary; each binary supplies its own `about`.
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Paths to the input images
+    #[arg(value_name = "FILES", num_args = 1..)]
+    pub paths: Vec<PathBuf>,
+
+    /// Download an image over HTTP(S) and render it after any FILES
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "URL")]
+    pub from_url: Option<String>,
+
+    /// Give up on a --from-url download after this many seconds
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "SECONDS", default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    pub url_timeout: u64,
+
+    /// Enable edge detection enhancement (Sharpening)
+    #[arg(short, long)]
+    pub edges: bool,
+
+    /// Sharpen before the resize (slower, keeps more detail from large
+    /// images) or after it
+    #[arg(long, value_enum, default_value_t = SharpenOrder::default())]
+    pub sharpen_order: SharpenOrder,
+
+    /// Channel that decides which braille dots are lit (braille mode only)
+    #[arg(long, value_enum, default_value_t = Channel::default())]
+    pub channel: Channel,
+
+    /// Weights for luma, which picks the braille dots, splits the
+    /// block/sextant/octant cells into two colors and picks ascii glyphs
+    #[arg(long, value_enum, default_value_t = LumaWeights::default())]
+    pub luma: LumaWeights,
+
+    /// How quadrant and sextant cells pick their glyph: split at the average
+    /// luma, or search every glyph for the closest match
+    #[arg(long = "match", value_enum, default_value_t = GlyphMatch::default())]
+    pub glyph_match: GlyphMatch,
+
+    /// Draw octant, sextant and quadrant cells whose luma varies by less
+    /// than this (0-1) as a space in the cell's average color, so flat
+    /// areas stay flat instead of picking up glyphs from noise
+    #[arg(long, value_name = "RANGE", value_parser = parse_min_contrast)]
+    pub min_contrast: Option<f32>,
+
+    /// What error diffusion does with error that would spill past the
+    /// image borders (braille, octant and dithered ascii)
+    #[arg(long, value_enum, default_value_t = EdgeHandling::default())]
+    pub edge_handling: EdgeHandling,
+
+    /// Level (0-255) braille dots turn on at, or `auto` for Otsu's
+    /// threshold over the whole image (braille mode only) [default: 128]
+    #[arg(long, value_name = "N|auto", conflicts_with = "dot_threshold_adaptive")]
+    pub threshold: Option<Threshold>,
+
+    /// Dither braille dots with an 8x8 Bayer pattern centered on the
+    /// threshold instead of diffusing error: the pattern doesn't depend on
+    /// the rest of the image, so output is repeatable and tiles cleanly
+    /// (braille mode only)
+    #[arg(long)]
+    pub ordered: bool,
+
+    /// Threshold braille dots against each cell's own mean luma instead of
+    /// mid-gray, keeping texture in shadows and highlights (braille mode only)
+    #[arg(long)]
+    pub dot_threshold_adaptive: bool,
+
+    /// Dither braille dots from linear luma raised to G, without touching
+    /// the dot colors: above the default curve's 0.2-0.5 thins out the
+    /// midtones, below fills them in (braille mode only)
+    #[arg(long, value_name = "G", value_parser = parse_luma_gamma)]
+    pub luma_gamma: Option<f32>,
+
+    /// Added to the adaptive threshold (0-255 scale); higher means fewer dots
+    #[arg(long, value_name = "OFFSET", default_value_t = 0.0, allow_negative_numbers = true, requires = "dot_threshold_adaptive")]
+    pub adaptive_offset: f32,
+
+    /// Specify the width of the output in characters, or as a percentage
+    /// of the terminal (e.g. 50%)
+    /// If not provided, the terminal width will be used.
+    #[arg(short, long)]
+    pub width: Option<Dimension>,
+
+    /// Specify the height of the output in characters, or as a percentage
+    /// of the terminal (e.g. 80%)
+    /// If not provided, the terminal height will be used.
+    #[arg(long)]
+    pub height: Option<Dimension>,
+
+    /// Never render wider than this (cells or percentage), while still
+    /// shrinking to fit smaller terminals
+    #[arg(long)]
+    pub max_width: Option<Dimension>,
+
+    /// Never render taller than this (cells or percentage), while still
+    /// shrinking to fit smaller terminals
+    #[arg(long)]
+    pub max_height: Option<Dimension>,
+
+    /// Rows to leave free at the bottom for the prompt when the height comes
+    /// from the terminal [default: 2]; 0 uses every row, as when writing to
+    /// a file. Ignored with --height
+    #[arg(long, value_name = "N")]
+    pub reserve_lines: Option<u32>,
+
+    /// Composite transparent areas over this color (#rgb or #rrggbb)
+    #[arg(long, value_name = "COLOR", value_parser = crate::color::parse_hex)]
+    pub background: Option<(u8, u8, u8)>,
+
+    /// Composite transparent areas over a gray checkerboard, as image
+    /// editors do, so they stand apart from white ones
+    #[arg(long, conflicts_with = "background")]
+    pub checker_transparency: bool,
+
+    /// The two colors of the --checker-transparency squares
+    #[arg(long, num_args = 2, value_names = ["DARK", "LIGHT"], value_parser = crate::color::parse_hex, requires = "checker_transparency")]
+    pub checker_colors: Option<Vec<(u8, u8, u8)>>,
+
+    /// Width of the --checker-transparency squares in cells (they're half
+    /// as many rows tall)
+    #[arg(long, value_name = "N", default_value_t = 2, value_parser = clap::value_parser!(u32).range(1..), requires = "checker_transparency")]
+    pub checker_size: u32,
+
+    /// How the pixels behind each cell color are averaged: linear light, or
+    /// Oklab to keep the hue of saturated multi-color cells
+    #[arg(long, value_enum, default_value_t = ColorAverage::default())]
+    pub color_average: ColorAverage,
+
+    /// Weight each pixel by its alpha when averaging a cell's colors, so
+    /// transparent pixels don't pull the color of a sprite's or logo's edge
+    /// cells toward whatever RGB they hold
+    #[arg(long = "avg-alpha-weight")]
+    pub alpha_weight: bool,
+
+    /// Invert the image's colors
+    #[arg(long)]
+    pub invert: bool,
+
+    /// Recolor braille and block renders along a gradient from DARK for
+    /// shadows to LIGHT for highlights, blended in Oklab
+    #[arg(long, num_args = 2, value_names = ["DARK", "LIGHT"], value_parser = crate::color::parse_hex, conflicts_with = "tint")]
+    pub duotone: Option<Vec<(u8, u8, u8)>>,
+
+    /// Quantize luma into N bands before picking glyph shapes (and duotone
+    /// colors), for a poster look; 2 gives stark black-and-white shapes
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(2..=256))]
+    pub posterize: Option<u32>,
+
+    /// Draw every braille dot in this color (#rgb or #rrggbb) instead of
+    /// the image's colors, keeping the dithered shape (braille mode only)
+    #[arg(long, value_name = "COLOR", value_parser = crate::color::parse_hex)]
+    pub tint: Option<(u8, u8, u8)>,
+
+    /// Leave cells that are mostly this color blank, e.g. #00ff00 or
+    /// #00ff00±40 to allow each channel to be off by up to 40
+    #[arg(long, value_name = "COLOR[±TOL]")]
+    pub keep_color: Option<KeyColor>,
+
+    /// Leave cells whose average luma is below this level (0-255) blank, so
+    /// near-black backgrounds become the terminal's own
+    #[arg(long, value_name = "LEVEL")]
+    pub luma_key: Option<u8>,
+
+    /// Swap the foreground and background of quadrant, sextant and octant
+    /// cells, so the glyph draws the darker part; the colors themselves are
+    /// unchanged
+    #[arg(long)]
+    pub invert_bg: bool,
+
+    /// Draw bright pixels with the dense end of the ascii ramp (`@`), for
+    /// light-on-dark terminals; by default dense glyphs are dark pixels
+    #[arg(long)]
+    pub ascii_ramp_invert: bool,
+
+    /// Flip whether each mode's glyphs draw the bright or the dark parts:
+    /// braille dots, block glyphs, shades and eighths bars go on dark
+    /// pixels, and the ascii ramp's dense end on bright ones
+    #[arg(long)]
+    pub invert_diffusion: bool,
+
+    /// Dithering between ascii ramp levels and --bits or --palette colors:
+    /// floyd-steinberg smooths gradients, none gives crisp bands
+    #[arg(long, value_enum, default_value_t = Dither::default())]
+    pub dither: Dither,
+
+    /// Round every drawn color to N bits per channel, e.g. 3 for 8 levels
+    /// each (512 colors), for a retro look
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=8))]
+    pub bits: Option<u8>,
+
+    /// Draw only the colors listed in FILE (one #rrggbb or GIMP-style
+    /// `R G B` per line), dithered between them with --dither
+    #[arg(long, value_name = "FILE", value_parser = palette::Palette::load, conflicts_with = "bits")]
+    pub palette: Option<palette::Palette>,
+
+    /// How the nearest color is picked for --palette, and how colors are
+    /// clustered for --dominant-color and --extract-palette: straight-line
+    /// distance in sRGB, in Oklab, or in CIE L*a*b* (CIE76)
+    #[arg(long, value_enum, default_value_t = ColorDistance::default())]
+    pub color_distance: ColorDistance,
+
+    /// How much of the --palette color error --dither spreads (0-1, 1 by
+    /// default): too much speckles colors that no entry matches, too little
+    /// bands them
+    #[arg(long, value_name = "STRENGTH", value_parser = parse_dither_strength, requires = "palette")]
+    pub palette_dither_strength: Option<f32>,
+
+    /// Invert braille renders when the terminal reports a light background
+    /// (best effort: terminals that don't answer get no inversion)
+    #[arg(long)]
+    pub auto_invert: bool,
+
+    /// Tile the image at its own size to fill the whole output instead of
+    /// scaling it, e.g. to make a background from a small pattern
+    #[arg(long)]
+    pub repeat: bool,
+
+    /// Fill the whole output (the terminal, or --width by --height),
+    /// distorting the image if its aspect ratio doesn't match
+    #[arg(long, conflicts_with_all = ["repeat", "pixel_aspect"])]
+    pub stretch: bool,
+
+    /// Downscale in sRGB gamma space instead of linear light (faster, but
+    /// thin bright features such as stars come out dimmer)
+    #[arg(long)]
+    pub gamma_resize: bool,
+
+    /// Keep thin bright features, such as stars, wires and hairlines,
+    /// through heavy downscaling: each pixel is pulled toward the brightest
+    /// one it covers, by how much that stands out. Brightens texture a
+    /// little, so tones are less exact
+    #[arg(long)]
+    pub weighted_resize: bool,
+
+    /// Dither braille dots at N times the resolution and light each dot by
+    /// how much of its NxN block came out on: fewer dither artifacts, N^2
+    /// times the work
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=8))]
+    pub supersample: u32,
+
+    /// Soften jagged braille edges by dimming the dots of cells along them
+    /// in proportion to how sparse they are: smoother, but less crisp
+    #[arg(long)]
+    pub antialias_edges: bool,
+
+    /// Draw braille dots without bold. Bold intensifies their colors, but
+    /// some terminals brighten or shift the color instead, or switch to a
+    /// bold font that distorts the braille glyphs
+    #[arg(long)]
+    pub no_bold: bool,
+
+    /// Diffuse octant color error right to left on every other row, which
+    /// cuts the rightward color smear on smooth ramps
+    #[arg(long)]
+    pub serpentine: bool,
+
+    /// How to bring the brightness of HDR inputs (OpenEXR, Radiance .hdr)
+    /// into displayable range
+    #[arg(long, value_enum, default_value_t = Tonemap::default())]
+    pub tonemap: Tonemap,
+
+    /// Decode the size nearest N pixels across from .ico and .cur files,
+    /// which hold several; by default the smallest that fills the output
+    /// without being scaled up
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub ico_size: Option<u32>,
+
+    /// How big still images are decoded: in full, or with auto, large PNG
+    /// and TIFF files are shrunk toward the output size as they decode, a
+    /// row or strip at a time, so memory stays proportional to the output
+    #[arg(long, value_enum, default_value_t = DecodeScale::default())]
+    pub decode_scale: DecodeScale,
+
+    /// Shape of the source's pixels as WIDTH:HEIGHT, e.g. 8:9 for
+    /// anamorphic video frames; the image is stretched to square pixels
+    /// before it is fit to the terminal cells
+    #[arg(long, value_name = "W:H", value_parser = parse_aspect)]
+    pub pixel_aspect: Option<f32>,
+
+    /// Ask the terminal how wide it draws the mode's glyphs, and if it's
+    /// two columns (as some fonts do for braille and sextants) render half
+    /// as many cells so the art doesn't wrap
+    #[arg(long)]
+    pub detect_glyph_width: bool,
+
+    /// How many columns the terminal draws each glyph across; 2 renders
+    /// half as many cells, for fonts that draw them double-width
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=2), conflicts_with = "detect_glyph_width")]
+    pub glyph_width: Option<u32>,
+
+    /// Fit the output into a WxH-cell region of the screen, X columns and Y
+    /// rows from its top-left corner, and draw it there like --at
+    #[arg(long, value_name = "WxH+X+Y", value_parser = parse_viewport, conflicts_with_all = ["at", "width", "height"])]
+    pub viewport: Option<Viewport>,
+
+    /// Draw the output with its top-left corner at this 1-based screen
+    /// position instead of at the cursor, e.g. to lay out a dashboard
+    #[arg(long, value_name = "ROW,COL", value_parser = parse_position)]
+    pub at: Option<(u32, u32)>,
+
+    /// Leave off blank cells at the ends of lines (those on the default
+    /// background), clearing to the end of the line instead
+    #[arg(long)]
+    pub trim_trailing: bool,
+
+    /// Print the image's main colors as #rrggbb, one per line and most
+    /// dominant first, instead of rendering it
+    #[arg(long)]
+    pub dominant_color: bool,
+
+    /// Print N colors representing the image as #rrggbb, one per line and
+    /// most common first (median cut in Oklab), instead of rendering it
+    #[arg(long, value_name = "N", conflicts_with = "dominant_color")]
+    pub extract_palette: Option<usize>,
+
+    /// Shrink images to fit SIZExSIZE before --extract-palette, for speed;
+    /// the full resolution is used otherwise
+    #[arg(long, value_name = "SIZE", requires = "extract_palette")]
+    pub palette_sample: Option<u32>,
+
+    /// Render each input in braille, octant, sextant, quadrant and halfblock
+    /// side by side, labeled, each sharing the width (or --width each)
+    #[arg(long, conflicts_with_all = ["gif_out", "png_out"])]
+    pub compare: bool,
+
+    /// Render the first COLSxROWS frames of each input as a contact sheet,
+    /// each labeled with its frame number and sharing the terminal (or
+    /// --width and --height each)
+    #[arg(long, value_name = "COLSxROWS", value_parser = parse_tile, conflicts_with_all = ["gif_out", "png_out", "compare", "frame"])]
+    pub tile: Option<(u32, u32)>,
+
+    /// Print how each mode divides a character cell into sub-pixels, then
+    /// exit
+    #[arg(long)]
+    pub help_modes: bool,
+
+    /// Print what was detected about the terminal and which optional
+    /// features this build has, as key=value lines, then exit
+    #[arg(long)]
+    pub capabilities: bool,
+
+    /// Print each input's format, size, color type, bit depth and frame
+    /// count instead of rendering it
+    #[arg(long, conflicts_with_all = ["dominant_color", "extract_palette"])]
+    pub probe: bool,
+
+    /// Print --dominant-color and --extract-palette colors as one JSON
+    /// array per input, and --probe metadata as one JSON object
+    #[arg(long)]
+    pub json: bool,
+
+    /// How to write glyphs outside ASCII: as UTF-8, or as \uXXXX escapes
+    /// for embedding in JSON strings or source files
+    #[arg(long, value_enum, default_value_t = OutputEncoding::default())]
+    pub output_encoding: OutputEncoding,
+
+    /// End each row of the art with a NUL byte instead of a newline, and
+    /// leave out the headers between files, so scripts can split the rows
+    /// exactly (e.g. with `read -d ''`)
+    #[arg(long, conflicts_with_all = ["at", "viewport"])]
+    pub output_lines: bool,
+
+    /// Shrink the output by merging back-to-back color changes and dropping
+    /// ones that change nothing; it looks the same
+    #[arg(long)]
+    pub strip: bool,
+
+    /// Print the rows bottom to top, for pipelines that consume lines in
+    /// that order
+    #[arg(long)]
+    pub vertical_flip_output: bool,
+
+    /// Rasterize the render of every frame (animated inputs) or file
+    /// (slideshow) into an animated GIF instead of printing it, each frame
+    /// written as it's rendered; the first frame sets the GIF's size
+    #[arg(long, value_name = "FILE")]
+    pub gif_out: Option<PathBuf>,
+
+    /// Rasterize the render into a PNG instead of printing it
+    /// Multiple inputs are stacked top to bottom.
+    #[arg(long, value_name = "FILE")]
+    pub png_out: Option<PathBuf>,
+
+    /// Pixel size of one character cell for --gif-out and --png-out
+    #[arg(long, value_name = "WxH", default_value = "8x16", value_parser = parse_cell_size)]
+    pub cell_size: (u32, u32),
+
+    /// Print the size of each render as `rows=R cols=C` to stderr once it
+    /// is drawn, so a calling program knows where the cursor ended up
+    #[arg(long)]
+    pub report_size: bool,
+
+    /// Shade every other cell's background in a faint checkerboard, to see
+    /// where cell boundaries fall when judging sizing and aspect
+    #[arg(long)]
+    pub cell_debug: bool,
+
+    /// Print cell, lit-cell, color and dot counts to stderr after each image
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Play animations at a constant N frames per second, ignoring their
+    /// own frame delays and dropping frames if rendering falls behind
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub fps: Option<u32>,
+
+    /// Multiply each animation frame's own delay by F, keeping their
+    /// relative timing: 0.5 plays twice as fast, 2 at half speed
+    #[arg(long, value_name = "F", value_parser = parse_delay_scale, conflicts_with = "fps")]
+    pub frame_delay_scale: Option<f32>,
+
+    /// Play animations over and over until interrupted, or N times in all
+    /// with --loop=N
+    #[arg(long = "loop", value_name = "N", num_args = 0..=1, require_equals = true, value_parser = clap::value_parser!(u32).range(1..))]
+    pub looping: Option<Option<u32>>,
+
+    /// Print MARKER and the pass number to stderr each time an animation
+    /// finishes a pass (--loop-notify=MARKER; "loop" by default), so
+    /// scripts can follow along
+    #[arg(long, value_name = "MARKER", num_args = 0..=1, require_equals = true, default_missing_value = "loop")]
+    pub loop_notify: Option<String>,
+
+    /// Wait for a keypress after rendering before exiting, so the shell
+    /// prompt doesn't scroll the art away; ignored unless run in a terminal
+    #[arg(long, conflicts_with_all = ["gif_out", "png_out"])]
+    pub hold: bool,
+
+    /// Fit the output to the width only and page through it with the arrow
+    /// and Page Up/Down keys (q to quit), for images taller than the terminal
+    #[arg(long, conflicts_with_all = ["gif_out", "png_out", "at", "viewport", "hold"])]
+    pub scroll: bool,
+
+    /// Render with the ASCII density ramp regardless of the terminal; by
+    /// default only terminals that look limited get a simpler glyph set
+    #[arg(long)]
+    pub ascii_fallback: bool,
+
+    /// Don't print a hint to stderr when the terminal is too narrow for the
+    /// mode to show much detail
+    #[arg(long)]
+    pub no_hints: bool,
+
+    /// Print only the art: no headers between files, and stop at the first
+    /// input that fails instead of reporting it and moving on
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Print the renders of several inputs straight after one another, with
+    /// no headers or blank lines between them, so pre-split pieces of one
+    /// image join back up
+    #[arg(long)]
+    pub no_reset_between_files: bool,
+
+    /// Show which input (and frame) is being processed on stderr; nothing is
+    /// shown when stderr isn't a terminal or under --quiet
+    #[arg(long)]
+    pub progress: bool,
+
+    /// Skip inputs that fail and carry on with the rest (the default without
+    /// --quiet; with it, failures are skipped silently)
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// Render only the first frame of animated inputs (same as --frame 0)
+    #[arg(long, conflicts_with = "frame")]
+    pub first_frame: bool,
+
+    /// Decode and show at most the first N frames of animated inputs, for a
+    /// quick preview of long ones
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["frame", "first_frame"])]
+    pub max_frames: Option<u32>,
+
+    /// Order of multiple FILES and of the images in a directory
+    #[arg(long, value_enum, default_value_t = SortFiles::default())]
+    pub sort_files: SortFiles,
+
+    /// Render only frame N (counting from 0) of animated inputs
+    #[arg(long, value_name = "N")]
+    pub frame: Option<usize>,
+}
+
+impl Args {
+    /// The mode to use when `preferred` wasn't asked for explicitly: it is
+    /// downgraded to what the terminal can likely show, or to ASCII with
+    /// --ascii-fallback.
+    pub fn fallback_mode(&self, preferred: Mode) -> Mode {
+        preferred.downgrade(self.glyph_support())
+    }
+
+    /// The glyphs the terminal can likely show, or ASCII with --ascii-fallback.
+    fn glyph_support(&self) -> GlyphSupport {
+        if self.ascii_fallback { GlyphSupport::Ascii } else { GlyphSupport::detect() }
+    }
+
+    /// Columns the terminal draws each of `mode`'s glyphs across: from
+    /// --glyph-width, measured once with --detect-glyph-width, or 1.
+    fn glyph_width(&self, mode: Mode) -> u32 {
+        static DETECTED: OnceLock<Option<u32>> = OnceLock::new();
+        match self.glyph_width {
+            Some(width) => width,
+            None if self.detect_glyph_width => DETECTED.get_or_init(|| theme::glyph_width(mode.sample_glyph())).unwrap_or(1),
+            None => 1,
+        }
+    }
+
+    /// Whether a failed input ends the run instead of being skipped.
+    pub fn stops_at_failure(&self) -> bool {
+        self.quiet && !self.keep_going
+    }
+
+    /// Every input in order: the FILES as sorted by --sort-files, then the
+    /// --from-url URL (which [`input_frames`] recognizes and downloads).
+    pub fn inputs(&self) -> Vec<PathBuf> {
+        let mut paths = self.paths.clone();
+        self.sort_files.sort(&mut paths);
+        let inputs = paths.into_iter();
+        #[cfg(feature = "http")]
+        let inputs = inputs.chain(self.from_url.as_ref().map(PathBuf::from));
+        inputs.collect()
+    }
+
+    /// The 1-based screen position to draw at, from --at or --viewport.
+    pub fn position(&self) -> Option<(u32, u32)> {
+        self.at.or(self.viewport.map(|v| (v.y + 1, v.x + 1)))
+    }
+
+    /// How long to show an animation frame whose own delay is `delay`:
+    /// scaled by --frame-delay-scale, but no shorter than [`MIN_FRAME_DELAY`].
+    fn frame_delay(&self, delay: Delay) -> Delay {
+        match self.frame_delay_scale {
+            Some(scale) => Delay::from_saturating_duration(Duration::from(delay).mul_f32(scale).max(MIN_FRAME_DELAY)),
+            None => delay,
+        }
+    }
+
+    /// The width to render at: --viewport's, or --width.
+    fn output_width(&self) -> Option<Dimension> {
+        self.viewport.map(|v| Dimension::Cells(v.width)).or(self.width)
+    }
+
+    /// The height to render at: --viewport's, --height, or tall enough for
+    /// anything with --scroll.
+    fn output_height(&self) -> Option<Dimension> {
+        self.viewport
+            .map(|v| Dimension::Cells(v.height))
+            .or(self.height)
+            .or(self.scroll.then_some(Dimension::Cells(SCROLL_MAX_ROWS)))
+    }
+
+    /// Which of the sizes in an icon or cursor file to decode: the one
+    /// nearest --ico-size, or the smallest that fills the output.
+    fn icon_entry(&self) -> IconEntry {
+        match self.ico_size {
+            Some(size) => IconEntry::Nearest(size),
+            None => {
+                let (width, height) = self.output_box();
+                IconEntry::Fit(width, height)
+            }
+        }
+    }
+
+    /// The box to shrink stills toward while decoding them, with
+    /// --decode-scale auto.
+    fn decode_shrink(&self) -> Option<(u32, u32)> {
+        (self.decode_scale == DecodeScale::Auto).then(|| self.output_box())
+    }
+
+    /// The size the output is fit into, in pixels.
+    fn output_box(&self) -> (u32, u32) {
+        crate::target_size(&RenderOptions {
+            width: self.output_width(),
+            height: self.output_height(),
+            max_width: self.max_width,
+            max_height: self.max_height,
+            reserve_lines: self.reserve_lines,
+            ..Default::default()
+        })
+    }
+
+    /// The single frame to render from each input, if one was requested.
+    pub fn frame_index(&self) -> Option<usize> {
+        if self.first_frame { Some(0) } else { self.frame }
+    }
+
+    pub fn render_options(&self, mode: Mode) -> RenderOptions {
+        RenderOptions {
+            mode,
+            edges: self.edges,
+            sharpen_order: self.sharpen_order,
+            channel: self.channel,
+            luma: self.luma,
+            posterize: self.posterize,
+            duotone: self.duotone.as_deref().map(|colors| (colors[0], colors[1])),
+            tint: self.tint,
+            glyph_match: self.glyph_match,
+            min_contrast: self.min_contrast,
+            ordered: self.ordered,
+            luma_gamma: self.luma_gamma,
+            threshold: if self.dot_threshold_adaptive {
+                Threshold::Adaptive(self.adaptive_offset)
+            } else {
+                self.threshold.unwrap_or_default()
+            },
+            width: self.output_width(),
+            height: self.output_height(),
+            max_width: self.max_width,
+            max_height: self.max_height,
+            reserve_lines: self.reserve_lines,
+            background: self.background,
+            color_average: self.color_average,
+            alpha_weight: self.alpha_weight,
+            checker: self.checker_transparency.then(|| {
+                let colors = self.checker_colors.as_deref().map(|colors| Checker { dark: colors[0], light: colors[1], ..Default::default() });
+                Checker { size: self.checker_size, ..colors.unwrap_or_default() }
+            }),
+            invert: self.invert || (self.auto_invert && mode == Mode::Braille && theme::is_light_background()),
+            invert_bg: self.invert_bg,
+            keep_color: self.keep_color,
+            luma_key: self.luma_key,
+            ascii_ramp_invert: self.ascii_ramp_invert,
+            invert_diffusion: self.invert_diffusion,
+            dither: self.dither,
+            bits: self.bits,
+            palette: self.palette.clone(),
+            color_distance: self.color_distance,
+            palette_dither_strength: self.palette_dither_strength,
+            repeat: self.repeat,
+            stretch: self.stretch,
+            edge_handling: self.edge_handling,
+            gamma_resize: self.gamma_resize,
+            weighted_resize: self.weighted_resize,
+            supersample: self.supersample,
+            antialias_edges: self.antialias_edges,
+            no_bold: self.no_bold,
+            serpentine: self.serpentine,
+            cell_debug: self.cell_debug,
+            pixel_aspect: self.pixel_aspect,
+            double_width: self.glyph_width(mode) == 2,
+        }
+    }
+}
+
+/// Entry point shared by the binaries: runs, prints any error and turns the
+/// outcome into the exit status.
+pub fn main(args: &Args, mode: Mode) -> ExitCode {
+    match run(args, mode) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // Each failed input was already reported, unless --quiet hid it
+            if !(args.quiet && matches!(e, JivError::InputsFailed(_))) {
+                eprintln!("Error: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Renders every input in `mode`, printing each one or capturing them all to
+/// a GIF or PNG. Inputs that fail are skipped (see [`Args::stops_at_failure`]),
+/// and then counted in a final [`JivError::InputsFailed`].
+pub fn run(args: &Args, mode: Mode) -> Result<()> {
+    if args.help_modes {
+        print_mode_legend();
+        return Ok(());
+    }
+    if args.capabilities {
+        print_capabilities(mode);
+        return Ok(());
+    }
+
+    if let Some(hint) = width_hint(args, mode) {
+        eprintln!("{}", hint);
+    }
+
+    let run = RunState {
+        failed: Cell::new(0),
+        progress: progress_bar(args),
+        #[cfg(feature = "http")]
+        downloaded: std::cell::RefCell::new(None),
+    };
+    run_inputs(args, mode, &run)?;
+    run.progress.finish_and_clear();
+    if args.hold && io::stdin().is_terminal() && io::stdout().is_terminal() {
+        wait_for_key()?;
+    }
+    match run.failed.get() {
+        0 => Ok(()),
+        n => Err(JivError::InputsFailed(n)),
+    }
+}
+
+/// A suggestion of a richer mode when renders would fill a terminal too
+/// narrow for `mode` to show much detail (see [`Mode::suggest_for_width`]).
+/// Only given when printing to a terminal at its own width.
+fn width_hint(args: &Args, mode: Mode) -> Option<String> {
+    let at_terminal_width = args.width.is_none() && args.viewport.is_none() && args.gif_out.is_none() && args.png_out.is_none();
+    if args.no_hints || args.quiet || !at_terminal_width || !io::stdout().is_terminal() {
+        return None;
+    }
+    let (columns, _) = terminal_size().ok()?;
+    let better = mode.suggest_for_width(columns, args.glyph_support())?;
+    Some(format!(
+        "hint: {} columns is narrow for {} mode (best from {}); {} mode fits more detail in (--no-hints hides this)",
+        columns,
+        mode,
+        mode.min_width(),
+        better
+    ))
+}
+
+/// Blocks until a key is pressed, reading it in raw mode so it isn't echoed
+/// and doesn't need Enter. The terminal is restored even if reading fails.
+fn wait_for_key() -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    let key = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break Ok(()),
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    key
+}
+
+/// What a run keeps track of across its inputs.
+struct RunState {
+    /// Inputs that failed and were skipped
+    failed: Cell<usize>,
+    /// The --progress bar, hidden when it wasn't asked for; anything printed
+    /// while it's up goes through `suspend`, so the two never interleave
+    progress: ProgressBar,
+    /// The --from-url download, kept for animations' later passes
+    #[cfg(feature = "http")]
+    downloaded: std::cell::RefCell<Option<Vec<u8>>>,
+}
+
+/// A bar counting through the inputs on stderr, or a hidden one unless
+/// --progress is given and stderr is a terminal (and not under --quiet).
+fn progress_bar(args: &Args) -> ProgressBar {
+    if !args.progress || args.quiet || !io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template("{spinner} processing file {pos}/{len} {msg}")
+        .expect("the progress template is valid");
+    let bar = ProgressBar::new(args.inputs().len() as u64).with_style(style).with_finish(ProgressFinish::AndClear);
+    bar.enable_steady_tick(PROGRESS_TICK);
+    bar
+}
+
+fn run_inputs(args: &Args, mode: Mode, run: &RunState) -> Result<()> {
+    let inputs = args.inputs();
+    if inputs.is_empty() {
+        eprintln!("No input files specified.");
+        return Ok(());
+    }
+
+    if let Some(gif_out) = &args.gif_out {
+        return capture_gif(args, gif_out, mode, run);
+    }
+
+    if let Some(png_out) = &args.png_out {
+        return capture_png(args, png_out, mode, run);
+    }
+
+    for (i, path) in inputs.iter().enumerate() {
+        run.progress.set_position(i as u64 + 1);
+        run.progress.set_message("");
+
+        // Print filename header if there are multiple files
+        if inputs.len() > 1 && args.position().is_none() && !args.quiet && !args.no_reset_between_files && !args.output_lines {
+            run.progress.suspend(|| println!("\n--- {} ---", path.display()));
+        }
+
+        let result = if args.probe {
+            run.progress.suspend(|| print_probe(path, args))
+        } else if args.compare {
+            print_compare(path, args, run)
+        } else if let Some(tile) = args.tile {
+            print_tile(path, args, mode, tile, run)
+        } else if args.dominant_color || args.extract_palette.is_some() {
+            print_colors(path, args, run)
+        } else if args.scroll {
+            scroll_image(path, args, mode, run)
+        } else {
+            render_image(path, args, mode, run)
+        };
+        if let Err(e) = result {
+            report_failure(args, path, e, run)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The frames of an input, honoring --frame and --max-frames. A directory is
+/// a sequence of the images in it, each held for the slide delay. Frames are
+/// decoded as they are reached, so a capped animation is never decoded past
+/// the cap.
+fn input_frames<'a>(
+    path: &Path,
+    args: &'a Args,
+    run: &'a RunState,
+) -> Result<Box<dyn Iterator<Item = Result<Frame>> + 'a>> {
+    let max_frames = args.max_frames.map_or(usize::MAX, |n| n as usize);
+    #[cfg(feature = "http")]
+    if let Some(url) = args.from_url.as_deref().filter(|&url| path.as_os_str() == url) {
+        // Downloaded once, even when an animation loops
+        let cached = run.downloaded.borrow().clone();
+        let bytes = match cached {
+            Some(bytes) => bytes,
+            None => {
+                let bytes = crate::fetch::fetch(url, Duration::from_secs(args.url_timeout))?;
+                *run.downloaded.borrow_mut() = Some(bytes.clone());
+                bytes
+            }
+        };
+        let mut frames = frames_from_memory(bytes, args.tonemap, args.icon_entry(), args.decode_shrink())?;
+        return match args.frame_index() {
+            Some(index) => {
+                let frame = frames.nth(index).unwrap_or_else(|| {
+                    Err(JivError::InvalidArg(format!("Frame {} is out of range for {}", index, url)))
+                });
+                Ok(Box::new(std::iter::once(frame)))
+            }
+            None => Ok(Box::new(frames.take(max_frames))),
+        };
+    }
+    if path.is_dir() {
+        let mut images = list_images(path)?;
+        args.sort_files.sort(&mut images);
+        if images.is_empty() {
+            return Err(JivError::InvalidArg(format!("No images found in {}", path.display())));
+        }
+        if let Some(index) = args.frame_index() {
+            if index >= images.len() {
+                return Err(JivError::InvalidArg(format!(
+                    "Frame {} is out of range: the directory has {} image(s)",
+                    index,
+                    images.len()
+                )));
+            }
+            images = vec![images.swap_remove(index)];
+        }
+
+        // Each slide decodes as it would given on its own, down to its first
+        // frame; one that fails is handled like any other failed input
+        let slides = images.into_iter().filter_map(move |image| {
+            match load_frame(&image, 0, args.tonemap, args.icon_entry(), args.decode_shrink()) {
+                Ok(frame) => Some(Ok(Frame::from_parts(frame.into_buffer(), 0, 0, Delay::from_numer_denom_ms(SLIDE_DELAY_MS, 1)))),
+                Err(e) => report_failure(args, &image, e, run).err().map(Err),
+            }
+        });
+        return Ok(Box::new(slides.take(max_frames)));
+    }
+
+    match args.frame_index() {
+        Some(index) => Ok(Box::new(std::iter::once(Ok(load_frame(path, index, args.tonemap, args.icon_entry(), args.decode_shrink())?)))),
+        None => Ok(Box::new(frames(path, args.tonemap, args.icon_entry(), args.decode_shrink())?.take(max_frames))),
+    }
+}
+
+/// Prints an input; animations play in place, each frame redrawn over the last.
+fn render_image(path: &Path, args: &Args, mode: Mode, run: &RunState) -> Result<()> {
+    let opts = args.render_options(mode);
+    let interval = args.fps.map(|fps| Duration::from_secs(1) / fps);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut stats = Stats::default();
+    let mut drawn_rows = 0;
+    let mut last_grid = Grid::new();
+    let (mut drawn, mut dropped) = (0u32, 0u32);
+    let mut first_drawn_at = None;
+    let mut last_drawn_at = Instant::now();
+    let mut due = Instant::now();
+    let mut passes = 0;
+
+    loop {
+        let mut frame_count = 0;
+        let mut frames = input_frames(path, args, run)?.peekable();
+        while let Some(frame) = frames.next() {
+            let frame = frame?;
+            frame_count += 1;
+            run.progress.set_message(format!("frame {}", frame_count));
+            let delay = interval.unwrap_or_else(|| args.frame_delay(frame.delay()).into());
+
+            // At a fixed rate, a frame is dropped once its successor is already
+            // due, but the last one is always shown so a pass ends where it should
+            let is_last = frames.peek().is_none();
+            if interval.is_some() && drawn > 0 && !is_last && Instant::now() >= due + delay {
+                dropped += 1;
+                due += delay;
+                continue;
+            }
+
+            // The frame is rendered while the previous one is still up
+            let grid = mode.render(&DynamicImage::ImageRgba8(frame.into_buffer()), &opts);
+            thread::sleep(due.saturating_duration_since(Instant::now()));
+            // NUL-terminated frames follow one another, as there are no
+            // lines to move back up over
+            let layout = AnsiOptions {
+                redraw_rows: if args.output_lines { 0 } else { drawn_rows },
+                at: args.position(),
+                trim_trailing: args.trim_trailing,
+                bottom_up: args.vertical_flip_output,
+                encoding: args.output_encoding,
+                nul_terminated: args.output_lines,
+            };
+            run.progress.suspend(|| {
+                write_grid(&grid, &layout, args, &mut out)?;
+                out.flush()
+            })?;
+
+            let now = Instant::now();
+            first_drawn_at.get_or_insert(now);
+            last_drawn_at = now;
+            // A fixed rate keeps to its schedule; GIF delays count from the draw
+            due = if interval.is_some() && drawn > 0 { due + delay } else { now + delay };
+
+            drawn += 1;
+            drawn_rows = grid.len();
+            stats.add_grid(&grid);
+            last_grid = grid;
+        }
+
+        passes += 1;
+        if let Some(marker) = args.loop_notify.as_ref().filter(|_| frame_count > 1) {
+            run.progress.suspend(|| eprintln!("{} {}", marker, passes));
+        }
+        let done = match args.looping {
+            None => true,
+            Some(limit) => limit.is_some_and(|n| passes >= n),
+        };
+        if done || frame_count <= 1 {
+            break;
+        }
+    }
+
+    if args.report_size {
+        report_size(&last_grid, run);
+    }
+
+    if args.stats {
+        run.progress.suspend(|| {
+            eprintln!("{}", stats);
+            if let Some(first) = first_drawn_at.filter(|_| drawn > 1) {
+                let fps = (drawn - 1) as f32 / (last_drawn_at - first).as_secs_f32();
+                eprintln!("fps: {:.1} ({} of {} frames dropped)", fps, dropped, drawn + dropped);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Writes a grid as ANSI laid out as `layout` says, run through
+/// [`optimize_ansi`] with --strip.
+fn write_grid(grid: &Grid, layout: &AnsiOptions, args: &Args, mut out: impl Write) -> io::Result<()> {
+    if !args.strip {
+        return write_ansi_with(grid, layout, out);
+    }
+    let mut buf = Vec::new();
+    write_ansi_with(grid, layout, &mut buf)?;
+    let ansi = String::from_utf8(buf).expect("ANSI output is valid UTF-8");
+    out.write_all(optimize_ansi(&ansi).as_bytes())
+}
+
+/// Pages through the render of an input's first frame (or --frame) for
+/// --scroll. Output that isn't a terminal just gets the whole render.
+fn scroll_image(path: &Path, args: &Args, mode: Mode, run: &RunState) -> Result<()> {
+    let Some(frame) = input_frames(path, args, run)?.next() else {
+        return Ok(());
+    };
+    let grid = mode.render(&DynamicImage::ImageRgba8(frame?.into_buffer()), &args.render_options(mode));
+    run.progress.suspend(|| {
+        if io::stdin().is_terminal() && io::stdout().is_terminal() {
+            pager::page(&grid, args.output_encoding)
+        } else {
+            let layout = AnsiOptions {
+                trim_trailing: args.trim_trailing,
+                encoding: args.output_encoding,
+                nul_terminated: args.output_lines,
+                ..Default::default()
+            };
+            write_grid(&grid, &layout, args, BufWriter::new(io::stdout().lock()))
+        }
+    })?;
+    Ok(())
+}
+
+/// Prints the --dominant-color or --extract-palette colors of an input's
+/// first frame (or --frame).
+fn print_colors(path: &Path, args: &Args, run: &RunState) -> Result<()> {
+    let Some(frame) = input_frames(path, args, run)?.next() else {
+        return Ok(());
+    };
+    let img = DynamicImage::ImageRgba8(frame?.into_buffer());
+
+    let colors = match args.extract_palette {
+        Some(n) => {
+            let img = match args.palette_sample {
+                Some(size) => img.thumbnail(size, size),
+                None => img,
+            };
+            palette::extract_palette(&img, n, args.color_distance)
+        }
+        None => palette::dominant_colors(&img, DOMINANT_COLORS, args.color_distance),
+    };
+
+    let hex: Vec<String> = colors.into_iter().map(to_hex).collect();
+    run.progress.suspend(|| {
+        if args.json {
+            let quoted: Vec<String> = hex.iter().map(|h| format!("\"{}\"", h)).collect();
+            println!("[{}]", quoted.join(", "));
+        } else {
+            for h in hex {
+                println!("{}", h);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Prints each mode's sub-pixel grid for --help-modes, drawn from
+/// [`Mode::cell_size`] with one `[]` per sub-pixel.
+fn print_mode_legend() {
+    for &mode in Mode::value_variants() {
+        let (columns, rows) = mode.cell_size();
+        let help = mode.to_possible_value().and_then(|v| v.get_help().map(|h| h.to_string())).unwrap_or_default();
+        // A cell is twice as tall as it is wide, so each sub-pixel is rows:2*columns
+        let divisor = gcd(rows, 2 * columns);
+        println!(
+            "{}: {}x{} sub-pixels per cell, {}x the resolution of one per cell, each {}:{} (width:height)",
+            mode,
+            columns,
+            rows,
+            columns * rows,
+            rows / divisor,
+            2 * columns / divisor
+        );
+        println!("  {}", help);
+        for _ in 0..rows {
+            println!("    {}", "[]".repeat(columns as usize));
+        }
+    }
+}
+
+/// Prints --capabilities, for bug reports: what the terminal looks like
+/// from here, and the mode that was picked for it.
+fn print_capabilities(mode: Mode) {
+    let env = |name| std::env::var(name).unwrap_or_default();
+    let size = terminal_size().map_or("unknown".to_string(), |(columns, rows)| format!("{}x{}", columns, rows));
+    println!("terminal_size={}", size);
+    println!("color_depth={}", color_depth(&env("TERM"), &env("COLORTERM")));
+    println!("term={}", env("TERM"));
+    println!("colorterm={}", env("COLORTERM"));
+    println!("stdout_tty={}", io::stdout().is_terminal());
+    println!("glyph_support={}", format!("{:?}", GlyphSupport::detect()).to_lowercase());
+    println!("mode={}", mode);
+    println!("feature.avif={}", cfg!(feature = "avif"));
+    println!("feature.http={}", cfg!(feature = "http"));
+    println!("feature.simd={}", cfg!(feature = "simd"));
+}
+
+/// The color depth `$TERM` and `$COLORTERM` advertise. Output is always
+/// truecolor; this only says whether the terminal claims to show it.
+fn color_depth(term: &str, colorterm: &str) -> &'static str {
+    if matches!(colorterm, "truecolor" | "24bit") || term.ends_with("-direct") {
+        "24bit"
+    } else if term.contains("256color") {
+        "256"
+    } else if term.is_empty() || term == "dumb" {
+        "none"
+    } else {
+        "16"
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Prints an input's first frame (or --frame) in every --compare mode.
+fn print_compare(path: &Path, args: &Args, run: &RunState) -> Result<()> {
+    let Some(frame) = input_frames(path, args, run)?.next() else {
+        return Ok(());
+    };
+    let img = DynamicImage::ImageRgba8(frame?.into_buffer());
+
+    let gaps = (COMPARE_MODES.len() - 1) * COMPARE_GAP;
+    let share = |columns: u32| (columns.saturating_sub(gaps as u32) / COMPARE_MODES.len() as u32).max(1);
+    let panels: Vec<(String, Grid)> = COMPARE_MODES
+        .iter()
+        .map(|&mode| {
+            let mut opts = args.render_options(mode);
+            opts.width.get_or_insert_with(|| Dimension::Cells(share(terminal_size().map_or(80, |(columns, _)| columns))));
+            (mode.to_string(), mode.render(&img, &opts))
+        })
+        .collect();
+    print_panels(&side_by_side(&panels, COMPARE_GAP), args, run)
+}
+
+/// Prints the first `columns` x `rows` frames of an input in `mode`, left
+/// to right and top to bottom, each labeled with its frame number. Inputs
+/// with fewer frames leave the rest of the sheet empty.
+fn print_tile(path: &Path, args: &Args, mode: Mode, (columns, rows): (u32, u32), run: &RunState) -> Result<()> {
+    let (term_w, term_h) = terminal_size().unwrap_or((80, 24));
+    let gaps = (columns - 1) * COMPARE_GAP as u32;
+    let reserved = args.reserve_lines.unwrap_or(crate::RESERVED_LINES);
+    let mut opts = args.render_options(mode);
+    opts.width.get_or_insert(Dimension::Cells((term_w.saturating_sub(gaps) / columns).max(1)));
+    // Each row of panels has its labels above it
+    opts.height.get_or_insert(Dimension::Cells((term_h.saturating_sub(reserved) / rows).saturating_sub(1).max(1)));
+
+    let mut panels = Vec::new();
+    for (index, frame) in input_frames(path, args, run)?.take((columns * rows) as usize).enumerate() {
+        run.progress.set_message(format!("frame {}", index + 1));
+        let img = DynamicImage::ImageRgba8(frame?.into_buffer());
+        panels.push((format!("frame {}", index), mode.render(&img, &opts)));
+    }
+    let grid: Grid = panels.chunks(columns as usize).flat_map(|row| side_by_side(row, COMPARE_GAP)).collect();
+    print_panels(&grid, args, run)
+}
+
+/// Prints a sheet of --compare or --tile panels.
+fn print_panels(grid: &Grid, args: &Args, run: &RunState) -> Result<()> {
+    let layout = AnsiOptions {
+        at: args.position(),
+        trim_trailing: args.trim_trailing,
+        bottom_up: args.vertical_flip_output,
+        encoding: args.output_encoding,
+        nul_terminated: args.output_lines,
+        ..Default::default()
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    run.progress.suspend(|| {
+        write_grid(grid, &layout, args, &mut out)?;
+        out.flush()
+    })?;
+    if args.report_size {
+        report_size(grid, run);
+    }
+    Ok(())
+}
+
+/// Prints the rows and columns `grid` took up, for --report-size.
+fn report_size(grid: &Grid, run: &RunState) {
+    let cols = grid.iter().map(Vec::len).max().unwrap_or(0);
+    run.progress.suspend(|| eprintln!("rows={} cols={}", grid.len(), cols));
+}
+
+fn print_probe(path: &Path, args: &Args) -> Result<()> {
+    let info = probe::probe(path)?;
+    let format = info.format.map_or("unknown".to_string(), |f| format!("{:?}", f).to_lowercase());
+    let color_type = format!("{:?}", info.color_type).to_lowercase();
+    let bit_depth = |unknown: &str| info.bit_depth().map_or(unknown.to_string(), |d| d.to_string());
+
+    if args.json {
+        println!(
+            "{{\"format\": \"{}\", \"width\": {}, \"height\": {}, \"color_type\": \"{}\", \"bit_depth\": {}, \"frames\": {}}}",
+            format, info.width, info.height, color_type, bit_depth("null"), info.frames
+        );
+    } else {
+        println!("format: {}", format);
+        println!("size: {}x{}", info.width, info.height);
+        println!("color type: {}", color_type);
+        println!("bit depth: {}", bit_depth("unknown"));
+        println!("frames: {}", info.frames);
+    }
+    Ok(())
+}
+
+fn capture_gif(args: &Args, gif_out: &Path, mode: Mode, run: &RunState) -> Result<()> {
+    let opts = args.render_options(mode);
+    let mut atlas = GlyphAtlas::new(args.cell_size.0, args.cell_size.1);
+    let mut gif = GifWriter::new(gif_out);
+
+    for (i, path) in args.inputs().iter().enumerate() {
+        run.progress.set_position(i as u64 + 1);
+        // Each frame is encoded as soon as it's rendered, so only one is held
+        // at a time; a file that breaks partway keeps the frames before it
+        let captured = input_frames(path, args, run).and_then(|decoded| {
+            let mut decoded = decoded.peekable();
+            let mut count = 0;
+            while let Some(frame) = decoded.next() {
+                let frame = frame?;
+                count += 1;
+                run.progress.set_message(format!("frame {}", count));
+                // A lone frame is a still (or one slide), held for the slide delay
+                let delay = if count == 1 && decoded.peek().is_none() {
+                    Delay::from_numer_denom_ms(SLIDE_DELAY_MS, 1)
+                } else {
+                    args.frame_delay(frame.delay())
+                };
+                let img = DynamicImage::ImageRgba8(frame.into_buffer());
+                let pixels = raster::rasterize(&mode.render(&img, &opts), &mut atlas);
+                // Failing to write stops the capture, rather than skipping the input
+                if let Err(e) = gif.push(Frame::from_parts(pixels, 0, 0, delay)) {
+                    return Ok(Err(e));
+                }
+            }
+            Ok(Ok(()))
+        });
+        match captured {
+            Ok(written) => written.map_err(JivError::Encode)?,
+            Err(e) => report_failure(args, path, e, run)?,
+        }
+    }
+
+    if gif.frames() == 0 {
+        return Err(JivError::InvalidArg("No frames were rendered".into()));
+    }
+    Ok(())
+}
+
+fn capture_png(args: &Args, png_out: &Path, mode: Mode, run: &RunState) -> Result<()> {
+    let opts = args.render_options(mode);
+    let mut grid = Grid::new();
+
+    for (i, path) in args.inputs().iter().enumerate() {
+        run.progress.set_position(i as u64 + 1);
+        // Animations contribute their first frame, directories every image
+        let count = if path.is_dir() { usize::MAX } else { 1 };
+        match input_frames(path, args, run).and_then(|frames| frames.take(count).collect::<Result<Vec<_>>>()) {
+            Ok(frames) => {
+                for frame in frames {
+                    grid.extend(mode.render(&DynamicImage::ImageRgba8(frame.into_buffer()), &opts));
+                }
+            }
+            Err(e) => report_failure(args, path, e, run)?,
+        }
+    }
+
+    if grid.is_empty() {
+        return Err(JivError::InvalidArg("No images were rendered".into()));
+    }
+    let mut atlas = GlyphAtlas::new(args.cell_size.0, args.cell_size.1);
+    raster::rasterize(&grid, &mut atlas).save(png_out).map_err(JivError::Encode)?;
+    Ok(())
+}
+
+/// Handles an input that failed: stops with its error under --quiet (unless
+/// --keep-going), and otherwise counts and reports it (unless --quiet) so the
+/// caller can move on to the next input.
+fn report_failure(args: &Args, path: &Path, e: JivError, run: &RunState) -> Result<()> {
+    if args.stops_at_failure() {
+        return Err(e);
+    }
+    run.failed.set(run.failed.get() + 1);
+    if !args.quiet {
+        run.progress.suspend(|| eprintln!("Error processing {}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+/// Parses a --tile layout such as `4x3`.
+fn parse_tile(s: &str) -> std::result::Result<(u32, u32), String> {
+    let (columns, rows) = s.split_once('x').ok_or("expected COLSxROWS, e.g. 4x3")?;
+    let columns: u32 = columns.parse().map_err(|_| format!("invalid column count: {}", columns))?;
+    let rows: u32 = rows.parse().map_err(|_| format!("invalid row count: {}", rows))?;
+    if columns == 0 || rows == 0 {
+        return Err("the sheet must be at least 1x1".into());
+    }
+    Ok((columns, rows))
+}
+
+/// Parses a `WxH` cell size such as `8x16`.
+fn parse_cell_size(s: &str) -> std::result::Result<(u32, u32), String> {
+    let (w, h) = s.split_once('x').ok_or("expected WxH, e.g. 8x16")?;
+    let w: u32 = w.parse().map_err(|_| format!("invalid cell width: {}", w))?;
+    let h: u32 = h.parse().map_err(|_| format!("invalid cell height: {}", h))?;
+    if w == 0 || h == 0 {
+        return Err("cell size must be at least 1x1".into());
+    }
+    Ok((w, h))
+}
+
+/// Parses a --luma-gamma exponent, which must be positive.
+fn parse_luma_gamma(s: &str) -> std::result::Result<f32, String> {
+    let gamma: f32 = s.trim().parse().map_err(|_| format!("invalid gamma: {}", s))?;
+    if !(gamma > 0.0 && gamma.is_finite()) {
+        return Err("the luma gamma must be positive".into());
+    }
+    Ok(gamma)
+}
+
+/// Parses a --min-contrast luma range, from 0 to 1.
+fn parse_min_contrast(s: &str) -> std::result::Result<f32, String> {
+    let range: f32 = s.trim().parse().map_err(|_| format!("invalid contrast: {}", s))?;
+    if !(0.0..=1.0).contains(&range) {
+        return Err("the minimum contrast must be between 0 and 1".into());
+    }
+    Ok(range)
+}
+
+/// Parses a --palette-dither-strength share, from 0 to 1.
+fn parse_dither_strength(s: &str) -> std::result::Result<f32, String> {
+    let strength: f32 = s.trim().parse().map_err(|_| format!("invalid strength: {}", s))?;
+    if !(0.0..=1.0).contains(&strength) {
+        return Err("the dither strength must be between 0 and 1".into());
+    }
+    Ok(strength)
+}
+
+/// Parses a --frame-delay-scale factor, which must be positive.
+fn parse_delay_scale(s: &str) -> std::result::Result<f32, String> {
+    let scale: f32 = s.trim().parse().map_err(|_| format!("invalid scale: {}", s))?;
+    if !(scale > 0.0 && scale.is_finite()) {
+        return Err("the delay scale must be positive".into());
+    }
+    Ok(scale)
+}
+
+/// Parses a `W:H` pixel aspect such as `8:9` into the width over the height.
+fn parse_aspect(s: &str) -> std::result::Result<f32, String> {
+    let (w, h) = s.split_once(':').ok_or("expected W:H, e.g. 8:9")?;
+    let w: f32 = w.trim().parse().map_err(|_| format!("invalid width: {}", w))?;
+    let h: f32 = h.trim().parse().map_err(|_| format!("invalid height: {}", h))?;
+    if !(w > 0.0 && h > 0.0 && w.is_finite() && h.is_finite()) {
+        return Err("both sides of the aspect must be positive".into());
+    }
+    Ok(w / h)
+}
+
+/// Parses a `WxH+X+Y` screen region such as `40x20+10+5`; the offsets
+/// count from 0 and may be left off.
+fn parse_viewport(s: &str) -> std::result::Result<Viewport, String> {
+    let (size, offsets) = s.split_once('+').unwrap_or((s, "0+0"));
+    let (width, height) = size.split_once('x').ok_or("expected WxH+X+Y, e.g. 40x20+10+5")?;
+    let (x, y) = offsets.split_once('+').ok_or("expected both offsets, e.g. 40x20+10+5")?;
+    let number = |n: &str, what: &str| n.trim().parse::<u32>().map_err(|_| format!("invalid {}: {}", what, n));
+    let viewport = Viewport {
+        width: number(width, "width")?,
+        height: number(height, "height")?,
+        x: number(x, "x offset")?,
+        y: number(y, "y offset")?,
+    };
+    if viewport.width == 0 || viewport.height == 0 {
+        return Err("the viewport must be at least 1x1".into());
+    }
+    Ok(viewport)
+}
+
+/// Parses a 1-based `ROW,COL` screen position such as `5,40`.
+fn parse_position(s: &str) -> std::result::Result<(u32, u32), String> {
+    let (row, col) = s.split_once(',').ok_or("expected ROW,COL, e.g. 5,40")?;
+    let row: u32 = row.trim().parse().map_err(|_| format!("invalid row: {}", row))?;
+    let col: u32 = col.trim().parse().map_err(|_| format!("invalid column: {}", col))?;
+    if row == 0 || col == 0 {
+        return Err("rows and columns start at 1".into());
+    }
+    Ok((row, col))
+}
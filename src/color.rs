@@ -0,0 +1,355 @@
+// The Oklab matrices are kept at the precision they were published with.
+#![allow(clippy::excessive_precision)]
+
+use image::{DynamicImage, Rgba, Rgba32FImage, RgbaImage};
+
+// --- Color space conversion functions ---
+pub fn srgb_to_linear(c: f32) -> f32 {
+    let c = unit(c);
+    let linear = if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    };
+    debug_assert!(linear.is_finite(), "srgb_to_linear({}) = {}", c, linear);
+    linear
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    let c = unit(c);
+    let srgb = if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    debug_assert!(srgb.is_finite(), "linear_to_srgb({}) = {}", c, srgb);
+    srgb
+}
+
+/// [`srgb_to_linear`] on every value in place, several at a time with the
+/// `simd` feature.
+pub fn srgb_to_linear_slice(values: &mut [f32]) {
+    #[cfg(feature = "simd")]
+    let values = crate::simd::convert(values, crate::simd::srgb_to_linear);
+    for v in values {
+        *v = srgb_to_linear(*v);
+    }
+}
+
+/// [`linear_to_srgb`] on every value in place, several at a time with the
+/// `simd` feature.
+pub fn linear_to_srgb_slice(values: &mut [f32]) {
+    #[cfg(feature = "simd")]
+    let values = crate::simd::convert(values, crate::simd::linear_to_srgb);
+    for v in values {
+        *v = linear_to_srgb(*v);
+    }
+}
+
+/// Clamps a channel to 0-1, treating NaN as 0, so a stray NaN or infinity
+/// from upstream math can't spread through error diffusion.
+fn unit(c: f32) -> f32 {
+    if c.is_nan() { 0.0 } else { c.clamp(0.0, 1.0) }
+}
+
+pub fn srgb_to_oklab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    // Convert sRGB [0,1] to linear
+    let r_lin = srgb_to_linear(r);
+    let g_lin = srgb_to_linear(g);
+    let b_lin = srgb_to_linear(b);
+    linear_to_oklab(r_lin, g_lin, b_lin)
+}
+
+pub fn oklab_to_srgb(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let (r_lin, g_lin, b_lin) = oklab_to_linear(l, a, b);
+    (
+        linear_to_srgb(r_lin),
+        linear_to_srgb(g_lin),
+        linear_to_srgb(b_lin),
+    )
+}
+
+pub fn linear_to_oklab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let (r, g, b) = (unit(r), unit(g), unit(b));
+    // 1. Linear RGB to LMS
+    let l = 0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b;
+    let m = 0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b;
+    let s = 0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b;
+    // 2. Cube root
+    let l_ = l.cbrt();
+    let m_ = m.cbrt();
+    let s_ = s.cbrt();
+    // 3. LMS to Oklab
+    let l = 0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_;
+    let a = 1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_;
+    let b = 0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_;
+    debug_assert!(l.is_finite() && a.is_finite() && b.is_finite(), "linear_to_oklab gave {:?}", (l, a, b));
+    (l, a, b)
+}
+
+pub fn oklab_to_linear(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    // 1. Oklab to LMS
+    let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+    let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+    let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+    // 2. Cube
+    let l = l_.powi(3);
+    let m = m_.powi(3);
+    let s = s_.powi(3);
+    // 3. LMS to linear RGB
+    let r = 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s;
+    let g = -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s;
+    let b = -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s;
+    debug_assert!(r.is_finite() && g.is_finite() && b.is_finite(), "oklab_to_linear gave {:?}", (r, g, b));
+    (r, g, b)
+}
+
+/// CIE L*a*b* of a linear-light sRGB color, against the D65 white point,
+/// with L* from 0 to 100.
+pub fn linear_to_lab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let (r, g, b) = (unit(r), unit(g), unit(b));
+    // 1. Linear RGB to XYZ, relative to the white point
+    let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / 1.08883;
+    // 2. Cube root, with a straight segment near black
+    let f = |t: f32| if t > 216.0 / 24389.0 { t.cbrt() } else { (24389.0 / 27.0 * t + 16.0) / 116.0 };
+    let (x, y, z) = (f(x), f(y), f(z));
+    // 3. XYZ to Lab
+    (116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z))
+}
+
+/// The weights luma is taken with, from gamma-encoded RGB.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LumaWeights {
+    /// Rec. 709 (HD video and sRGB)
+    #[default]
+    Rec709,
+    /// Rec. 601 (SD video and most older material)
+    Rec601,
+    /// The plain mean of the three channels
+    Average,
+}
+
+impl LumaWeights {
+    /// The red, green and blue weights, which sum to 1.
+    pub fn weights(self) -> [f32; 3] {
+        match self {
+            LumaWeights::Rec709 => [0.2126, 0.7152, 0.0722],
+            LumaWeights::Rec601 => [0.299, 0.587, 0.114],
+            LumaWeights::Average => [1.0 / 3.0; 3],
+        }
+    }
+
+    /// Luma of a gamma-encoded color, on the same scale as its channels.
+    pub fn luma(self, r: f32, g: f32, b: f32) -> f32 {
+        let [wr, wg, wb] = self.weights();
+        wr * r + wg * g + wb * b
+    }
+}
+
+/// An 8-bit sRGB color.
+pub type Rgb8 = (u8, u8, u8);
+
+/// The color a fraction `t` (0-1) of the way from `dark` to `light`,
+/// interpolated in Oklab so the steps look even.
+pub fn duotone(dark: Rgb8, light: Rgb8, t: f32) -> Rgb8 {
+    let to_oklab = |(r, g, b): Rgb8| srgb_to_oklab(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let (dark, light) = (to_oklab(dark), to_oklab(light));
+    let t = t.clamp(0.0, 1.0);
+    let lerp = |d: f32, l: f32| d + (l - d) * t;
+
+    let (r, g, b) = oklab_to_srgb(lerp(dark.0, light.0), lerp(dark.1, light.1), lerp(dark.2, light.2));
+    let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    (to_u8(r), to_u8(g), to_u8(b))
+}
+
+/// How the pixels of a cell, or of one color group within it, are averaged
+/// into a single color.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorAverage {
+    /// Mean in linear light, as the pixels' light would blend: fast, but a
+    /// mix of saturated hues can come out dull or shifted
+    #[default]
+    Linear,
+    /// Mean in Oklab, which keeps the perceived hue of saturated mixes at
+    /// the cost of a conversion per pixel
+    Oklab,
+}
+
+impl ColorAverage {
+    /// The mean of the linear-light `colors`, in linear light, taken as if
+    /// there were `count` of them: any past those given count as black.
+    pub fn mean(self, colors: &[(f32, f32, f32)], count: f32) -> (f32, f32, f32) {
+        self.average(colors.iter().map(|&c| (c, 1.0)), count)
+    }
+
+    /// The mean of the linear-light `colors`, each counting for as much as
+    /// its entry in `weights` (such as its alpha). `None` when the weights
+    /// add up to nothing.
+    pub fn weighted_mean(self, colors: &[(f32, f32, f32)], weights: &[f32]) -> Option<(f32, f32, f32)> {
+        let total: f32 = weights.iter().sum();
+        (total > 0.0).then(|| self.average(colors.iter().copied().zip(weights.iter().copied()), total))
+    }
+
+    /// The sum of the weighted `colors`, divided by `total`.
+    fn average(self, mut colors: impl Iterator<Item = ((f32, f32, f32), f32)>, total: f32) -> (f32, f32, f32) {
+        let sum = |colors: &mut dyn Iterator<Item = ((f32, f32, f32), f32)>| {
+            colors.fold((0.0, 0.0, 0.0), |s, (c, w)| (s.0 + c.0 * w, s.1 + c.1 * w, s.2 + c.2 * w))
+        };
+        match self {
+            ColorAverage::Linear => {
+                let (r, g, b) = sum(&mut colors);
+                (r / total, g / total, b / total)
+            }
+            ColorAverage::Oklab => {
+                let (l, a, b) = sum(&mut colors.map(|((r, g, b), w)| (linear_to_oklab(r, g, b), w)));
+                let (r, g, b) = oklab_to_linear(l / total, a / total, b / total);
+                (unit(r), unit(g), unit(b))
+            }
+        }
+    }
+}
+
+/// Squared Euclidean distance between two Oklab colors, which tracks
+/// perceived difference far better than distance in RGB.
+pub fn oklab_distance(x: (f32, f32, f32), y: (f32, f32, f32)) -> f32 {
+    squared_distance(x, y)
+}
+
+/// How the difference between two colors is measured where the closest of
+/// several is picked: matching a --palette, and clustering colors for
+/// --dominant-color and --extract-palette.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorDistance {
+    /// Straight-line distance between sRGB values: cheap, but it overstates
+    /// differences among greens and understates them among blues
+    Rgb,
+    /// Distance in Oklab, which tracks perceived difference closely
+    #[default]
+    Oklab,
+    /// CIE76: distance in CIE L*a*b*, the long-standing standard
+    Cie76,
+}
+
+impl ColorDistance {
+    /// Where a linear-light color sits in the space this metric measures
+    /// straight-line distance in.
+    pub fn coordinates(self, (r, g, b): (f32, f32, f32)) -> (f32, f32, f32) {
+        match self {
+            ColorDistance::Rgb => (linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b)),
+            ColorDistance::Oklab => linear_to_oklab(r, g, b),
+            ColorDistance::Cie76 => linear_to_lab(r, g, b),
+        }
+    }
+}
+
+/// Squared distance between two linear-light colors by `metric`; it only
+/// compares with other distances by the same metric.
+pub fn color_distance(x: (f32, f32, f32), y: (f32, f32, f32), metric: ColorDistance) -> f32 {
+    squared_distance(metric.coordinates(x), metric.coordinates(y))
+}
+
+/// Squared straight-line distance between two points of a color space.
+pub(crate) fn squared_distance(x: (f32, f32, f32), y: (f32, f32, f32)) -> f32 {
+    (x.0 - y.0).powi(2) + (x.1 - y.1).powi(2) + (x.2 - y.2).powi(2)
+}
+// --- End color space conversion functions ---
+
+/// Converts an image to linear-light RGBA (alpha is left as is).
+pub fn image_to_linear(img: &DynamicImage) -> Rgba32FImage {
+    let mut linear = img.to_rgba32f();
+    // Decoding the whole image at once lets the simd feature batch it
+    let mut rgb: Vec<f32> = linear.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+    srgb_to_linear_slice(&mut rgb);
+    for (p, rgb) in linear.pixels_mut().zip(rgb.chunks_exact(3)) {
+        p.0[..3].copy_from_slice(rgb);
+    }
+    linear
+}
+
+/// Converts a linear-light RGBA image back to 8-bit sRGB, clamping any
+/// out-of-range values (e.g. Lanczos ringing).
+pub fn linear_image_to_srgb8(img: &Rgba32FImage) -> RgbaImage {
+    let mut rgb: Vec<f32> = img.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+    linear_to_srgb_slice(&mut rgb);
+    let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut out = RgbaImage::new(img.width(), img.height());
+    for ((p, rgb), linear) in out.pixels_mut().zip(rgb.chunks_exact(3)).zip(img.pixels()) {
+        *p = Rgba([to_u8(rgb[0]), to_u8(rgb[1]), to_u8(rgb[2]), to_u8(linear[3])]);
+    }
+    out
+}
+
+/// Blends every pixel over an opaque sRGB `background` in linear light,
+/// leaving the image fully opaque.
+pub fn composite_over(img: &mut RgbaImage, background: (u8, u8, u8)) {
+    composite_over_with(img, |_, _| background);
+}
+
+/// A checkerboard of two colors to show transparency against, as image
+/// editors do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checker {
+    pub dark: Rgb8,
+    pub light: Rgb8,
+    /// Width of a square in cells; squares are half as many rows tall, so
+    /// they look square
+    pub size: u32,
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Checker { dark: (102, 102, 102), light: (153, 153, 153), size: 2 }
+    }
+}
+
+/// Blends every pixel over `checker` in linear light, with squares
+/// `width` x `height` pixels, leaving the image fully opaque. The top-left
+/// square is light.
+pub fn composite_over_checker(img: &mut RgbaImage, checker: Checker, (width, height): (u32, u32)) {
+    let (width, height) = (width.max(1), height.max(1));
+    composite_over_with(img, |x, y| if (x / width + y / height) % 2 == 0 { checker.light } else { checker.dark });
+}
+
+/// Blends every pixel over the opaque sRGB color `background(x, y)` gives
+/// for it, in linear light.
+fn composite_over_with(img: &mut RgbaImage, background: impl Fn(u32, u32) -> Rgb8) {
+    let mut mixed = Vec::with_capacity(img.len() / 4 * 3);
+    for (x, y, p) in img.enumerate_pixels() {
+        let (r, g, b) = background(x, y);
+        let bg = [r, g, b].map(|c| srgb_to_linear(c as f32 / 255.0));
+        let alpha = p[3] as f32 / 255.0;
+        for c in 0..3 {
+            mixed.push(srgb_to_linear(p[c] as f32 / 255.0) * alpha + bg[c] * (1.0 - alpha));
+        }
+    }
+    // Encoding the whole image at once lets the simd feature batch it
+    linear_to_srgb_slice(&mut mixed);
+    for (p, mixed) in img.pixels_mut().zip(mixed.chunks_exact(3)) {
+        for c in 0..3 {
+            p[c] = (mixed[c].clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        p[3] = 255;
+    }
+}
+
+/// Parses a hex color such as `#1e1e2e` or `fff`.
+pub fn parse_hex(s: &str) -> Result<(u8, u8, u8), String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("invalid hex color: {}", s))?;
+    match digits[..] {
+        [r, g, b] => Ok((r * 17, g * 17, b * 17)),
+        [r1, r2, g1, g2, b1, b2] => Ok((r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2)),
+        _ => Err(format!("expected #rgb or #rrggbb: {}", s)),
+    }
+}
+
+/// Formats an sRGB color as `#rrggbb`.
+pub fn to_hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
@@ -0,0 +1,177 @@
+use crate::cell::Grid;
+use crate::color::{ColorDistance, Rgb8, srgb_to_linear};
+use crate::palette::Palette;
+
+/// The Stucki kernel, as `(dx, dy, weight)`: spreads error over two rows
+/// ahead, for smoother dither than Floyd-Steinberg at a little more work.
+pub(crate) const STUCKI: &[(i32, i32, f32)] = &[
+    (1, 0, 8.0 / 42.0), (2, 0, 4.0 / 42.0),
+    (-2, 1, 2.0 / 42.0), (-1, 1, 4.0 / 42.0), (0, 1, 8.0 / 42.0), (1, 1, 4.0 / 42.0), (2, 1, 2.0 / 42.0),
+    (-2, 2, 1.0 / 42.0), (-1, 2, 2.0 / 42.0), (0, 2, 4.0 / 42.0), (1, 2, 2.0 / 42.0), (2, 2, 1.0 / 42.0),
+];
+
+/// The Floyd-Steinberg kernel, as `(dx, dy, weight)`.
+pub(crate) const FLOYD_STEINBERG: &[(i32, i32, f32)] = &[
+    (1, 0, 7.0 / 16.0),
+    (-1, 1, 3.0 / 16.0), (0, 1, 5.0 / 16.0), (1, 1, 1.0 / 16.0),
+];
+
+/// The 8x8 Bayer index matrix: every cell of an 8x8 tile ranked so that
+/// each threshold level is spread as evenly as possible.
+const BAYER_8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// The ordered-dither threshold at (`x`, `y`), in (0, 1): the Bayer rank of
+/// the pixel's place in its 8x8 tile, centered in its step.
+pub(crate) fn bayer(x: u32, y: u32) -> f32 {
+    (BAYER_8[(y % 8) as usize][(x % 8) as usize] as f32 + 0.5) / 64.0
+}
+
+/// Whether the ascii mode dithers the ramp level of each cell, and whether
+/// colors reduced to fewer bits or to a palette carry their error across
+/// cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Dither {
+    /// Round each cell to the nearest ramp glyph, leaving visible bands
+    None,
+    /// Carry each cell's rounding error to its neighbors, so gradients
+    /// blend between ramp levels
+    #[default]
+    FloydSteinberg,
+}
+
+/// What error diffusion does with the share of an error whose kernel target
+/// falls outside the image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum EdgeHandling {
+    /// Lose it, so tone drifts a little along the borders
+    #[default]
+    Drop,
+    /// Scale up the in-bounds weights so the whole error is still spread
+    Renormalize,
+    /// Reflect the target back into the image across the border
+    Mirror,
+}
+
+/// Spreads an error from pixel (`x`, `y`) with `kernel`, a list of
+/// `(dx, dy, weight)`, calling `add(nx, ny, weight)` for each target that
+/// ends up inside the `width` x `height` image.
+pub(crate) fn diffuse(
+    kernel: &[(i32, i32, f32)],
+    (x, y): (i32, i32),
+    (width, height): (i32, i32),
+    edges: EdgeHandling,
+    mut add: impl FnMut(usize, usize, f32),
+) {
+    let inside = |nx: i32, ny: i32| nx >= 0 && nx < width && ny >= 0 && ny < height;
+    let reflect = |n: i32, len: i32| {
+        if n < 0 {
+            -n - 1
+        } else if n >= len {
+            2 * len - n - 1
+        } else {
+            n
+        }
+    };
+
+    let scale = match edges {
+        EdgeHandling::Renormalize => {
+            let total: f32 = kernel.iter().map(|&(_, _, w)| w).sum();
+            let kept: f32 = kernel.iter().filter(|&&(dx, dy, _)| inside(x + dx, y + dy)).map(|&(_, _, w)| w).sum();
+            if kept > 0.0 { total / kept } else { 0.0 }
+        }
+        _ => 1.0,
+    };
+
+    for &(dx, dy, weight) in kernel {
+        let (mut nx, mut ny) = (x + dx, y + dy);
+        if edges == EdgeHandling::Mirror {
+            nx = reflect(nx, width);
+            ny = reflect(ny, height);
+        }
+        // Tiny images can reflect right past the far border too
+        if inside(nx, ny) {
+            add(nx as usize, ny as usize, weight * scale);
+        }
+    }
+}
+
+/// Rounds every foreground and background color in `grid` to `bits` bits
+/// per channel, carrying each channel's rounding error to the neighboring
+/// cells' colors of the same kind when `dither` asks for it.
+pub(crate) fn reduce_bits(grid: &mut Grid, bits: u8, dither: Dither, edges: EdgeHandling) {
+    let steps = ((1u32 << bits) - 1) as f32;
+    let quantize = |v: f32| (v.clamp(0.0, 255.0) * steps / 255.0).round() * 255.0 / steps;
+    quantize_cells(grid, (dither, 1.0), edges, |c| c as f32, |level| {
+        let rounded = level.map(quantize);
+        ((rounded[0] as u8, rounded[1] as u8, rounded[2] as u8), rounded)
+    });
+}
+
+/// Replaces every foreground and background color in `grid` with its
+/// nearest `palette` entry by `metric`. The error is carried in linear
+/// light, so a dithered area keeps the brightness of the original; `dither`
+/// comes with the share of it to carry.
+pub(crate) fn reduce_to_palette(
+    grid: &mut Grid,
+    palette: &Palette,
+    metric: ColorDistance,
+    dither: (Dither, f32),
+    edges: EdgeHandling,
+) {
+    let nearest = palette.nearest_by(metric);
+    quantize_cells(grid, dither, edges, |c| srgb_to_linear(c as f32 / 255.0), |level| {
+        nearest(level.map(|c| c.clamp(0.0, 1.0)))
+    });
+}
+
+/// Swaps each cell color for the one `pick` chooses, working on the
+/// channels as mapped by `to_work` (which must be increasing). `pick` returns the chosen color along
+/// with its working channels; with `Dither::FloydSteinberg` the difference,
+/// scaled by the strength that comes with it, is spread to the neighboring
+/// cells' colors of the same kind.
+fn quantize_cells(
+    grid: &mut Grid,
+    (dither, strength): (Dither, f32),
+    edges: EdgeHandling,
+    to_work: impl Fn(u8) -> f32,
+    pick: impl Fn([f32; 3]) -> (Rgb8, [f32; 3]),
+) {
+    let (low, high) = (to_work(0), to_work(255));
+    let (rows, columns) = (grid.len(), grid.iter().map(Vec::len).max().unwrap_or(0));
+    let mut fg_error = vec![vec![[0.0f32; 3]; columns]; rows];
+    let mut bg_error = fg_error.clone();
+    for y in 0..rows {
+        for x in 0..grid[y].len() {
+            let cell = &mut grid[y][x];
+            for (color, error) in [(&mut cell.fg, &mut fg_error), (&mut cell.bg, &mut bg_error)] {
+                let Some((r, g, b)) = *color else {
+                    continue;
+                };
+                let wanted = [r, g, b].map(&to_work);
+                // Clamped a whole range beyond either end: well clear of what
+                // reachable colors carry, while a color the choices can't
+                // reach doesn't pile up error without bound
+                let level: [f32; 3] = std::array::from_fn(|c| (wanted[c] + error[y][x][c]).clamp(2.0 * low - high, 2.0 * high - low));
+                let (got, got_work) = pick(level);
+                *color = Some(got);
+                if dither == Dither::FloydSteinberg {
+                    let residual: [f32; 3] = std::array::from_fn(|c| (level[c] - got_work[c]) * strength);
+                    diffuse(FLOYD_STEINBERG, (x as i32, y as i32), (columns as i32, rows as i32), edges, |nx, ny, weight| {
+                        for c in 0..3 {
+                            error[ny][nx][c] += residual[c] * weight;
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::cell::Grid;
+use crate::{Mode, RenderOptions, Renderer};
+use image::DynamicImage;
+
+/// Fill levels from empty to full: a space, then the lower blocks one
+/// eighth to eight eighths tall.
+pub const LEVELS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Lower eighth blocks as tall as the luma, as a [`Renderer`]; see [`render`].
+pub struct Eighths;
+
+impl Renderer for Eighths {
+    fn cell_size(&self) -> (u32, u32) {
+        (1, 2)
+    }
+
+    fn render(&self, img: &DynamicImage, opts: &RenderOptions) -> Grid {
+        render(img, opts)
+    }
+}
+
+/// Renders an image as bars: each 1x2 cell becomes the lower block as tall
+/// as its average luma, so brighter is fuller (darker with
+/// `opts.invert_diffusion`), Floyd-Steinberg dithered across cells unless
+/// `opts.dither` is off. The bars are drawn in the terminal's own colors,
+/// so no escape codes are emitted; the fill alone carries the brightness,
+/// which suits spectrograms and 1D signals.
+pub fn render(img: &DynamicImage, opts: &RenderOptions) -> Grid {
+    crate::ascii::render_ramp(img, opts, Mode::Eighths, &LEVELS, |luma| {
+        let luma = opts.posterize(luma);
+        if opts.invert_diffusion { 1.0 - luma } else { luma }
+    })
+}
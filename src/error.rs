@@ -0,0 +1,56 @@
+use image::{ImageError, ImageFormat};
+use std::io;
+
+/// Everything that can go wrong loading, rendering or writing an image.
+#[derive(Debug, thiserror::Error)]
+pub enum JivError {
+    /// Reading an input or writing the output failed
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The input is in a known format but couldn't be decoded
+    #[error("Failed to open image: {0}")]
+    Decode(#[from] ImageError),
+
+    /// The input's format isn't one this build can decode
+    #[error(
+        "Failed to open image: {source}\nSupported formats: {}{}",
+        crate::frames::supported_formats(),
+        avif_hint(*.format)
+    )]
+    UnsupportedFormat {
+        /// The format the input was detected as, if any
+        format: Option<ImageFormat>,
+        source: ImageError,
+    },
+
+    /// Encoding the --gif-out or --png-out image failed
+    #[error("Failed to write image: {0}")]
+    Encode(#[source] ImageError),
+
+    /// Downloading a --from-url input failed, or it wasn't an image
+    #[error("Failed to fetch image: {0}")]
+    Fetch(String),
+
+    /// An option doesn't fit the input, e.g. a frame past the end
+    #[error("{0}")]
+    InvalidArg(String),
+
+    /// Some inputs failed and were skipped; each was reported as it happened
+    #[error("{0} input(s) failed")]
+    InputsFailed(usize),
+
+    /// The terminal size couldn't be read (rendering falls back to 80x24)
+    #[error("Failed to read the terminal size: {0}")]
+    TerminalSize(#[source] io::Error),
+}
+
+fn avif_hint(format: Option<ImageFormat>) -> &'static str {
+    if format == Some(ImageFormat::Avif) {
+        "\nAVIF decoding needs a build with `--features avif` (requires libdav1d)"
+    } else {
+        ""
+    }
+}
+
+pub type Result<T> = std::result::Result<T, JivError>;
@@ -0,0 +1,28 @@
+use crate::error::{JivError, Result};
+use std::time::Duration;
+
+/// Largest download --from-url will read into memory.
+const MAX_DOWNLOAD_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Downloads `url` into memory, following redirects, giving up after
+/// `timeout` overall. Responses that say they aren't images are refused
+/// before their body is read; those that don't say are left to the decoder.
+pub fn fetch(url: &str, timeout: Duration) -> Result<Vec<u8>> {
+    let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(timeout)).build().into();
+    let mut response = agent.get(url).call().map_err(|e| JivError::Fetch(format!("{}: {}", url, e)))?;
+
+    let body = response.body_mut();
+    if let Some(mime) = body.mime_type().filter(|mime| !is_image_mime(mime)) {
+        return Err(JivError::Fetch(format!("{} is {}, not an image", url, mime)));
+    }
+    body.with_config()
+        .limit(MAX_DOWNLOAD_BYTES)
+        .read_to_vec()
+        .map_err(|e| JivError::Fetch(format!("{}: {}", url, e)))
+}
+
+/// Whether a content type could hold an image: `image/*`, or the generic
+/// binary type some servers send for everything.
+fn is_image_mime(mime: &str) -> bool {
+    mime.starts_with("image/") || mime == "application/octet-stream"
+}
@@ -0,0 +1,216 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
+use crate::error::{JivError, Result};
+use crate::icon::{self, IconEntry};
+use crate::shrink;
+use crate::tonemap::{self, Tonemap};
+use image::{AnimationDecoder, DynamicImage, Frame, ImageError, ImageFormat, ImageReader};
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{self, BufRead, Cursor, Read, Seek};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+
+/// Streams the frames of an animated GIF or WebP, decoding each one only when
+/// it is reached. Any other input (including a still WebP) yields a single
+/// frame with no delay, tone-mapped with `tonemap` if it holds
+/// floating-point (HDR) values. Of the sizes in an icon or cursor file,
+/// `icon` picks the one decoded. With a `shrink` box, PNG and TIFF stills
+/// are shrunk toward it as they decode; see [`shrink::DecodeScale::Auto`].
+pub fn frames(
+    path: &Path,
+    tonemap: Tonemap,
+    icon: IconEntry,
+    shrink: Option<(u32, u32)>,
+) -> Result<Box<dyn Iterator<Item = Result<Frame>>>> {
+    decode_frames(ImageReader::open(path)?.with_guessed_format()?, tonemap, icon, shrink)
+}
+
+/// Like [`frames`], for an image already read into memory, e.g. a download.
+pub fn frames_from_memory(
+    bytes: Vec<u8>,
+    tonemap: Tonemap,
+    icon: IconEntry,
+    shrink: Option<(u32, u32)>,
+) -> Result<Box<dyn Iterator<Item = Result<Frame>>>> {
+    decode_frames(ImageReader::new(Cursor::new(bytes)).with_guessed_format()?, tonemap, icon, shrink)
+}
+
+fn decode_frames<R: BufRead + Seek + 'static>(
+    reader: ImageReader<R>,
+    tonemap: Tonemap,
+    icon: IconEntry,
+    shrink: Option<(u32, u32)>,
+) -> Result<Box<dyn Iterator<Item = Result<Frame>>>> {
+    match reader.format() {
+        format @ (Some(ImageFormat::Ico) | None) => {
+            let img = decode_icon(reader.into_inner(), format, icon)?;
+            Ok(Box::new(std::iter::once(Ok(Frame::new(tonemap::to_rgba8(img, tonemap))))))
+        }
+        Some(ImageFormat::Gif) => {
+            let decoder = GifDecoder::new(reader.into_inner())?;
+            Ok(Box::new(decoder.into_frames().map(|frame| frame.map_err(JivError::from))))
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(reader.into_inner())?;
+            if decoder.has_animation() {
+                Ok(Box::new(decoder.into_frames().map(|frame| frame.map_err(JivError::from))))
+            } else {
+                let img = DynamicImage::from_decoder(decoder)?;
+                Ok(Box::new(std::iter::once(Ok(Frame::new(img.to_rgba8())))))
+            }
+        }
+        Some(format) => {
+            let mut input = reader.into_inner();
+            if let Some(fit) = shrink
+                && let Some(shrunk) = shrink::decode(&mut input, format, fit)?
+            {
+                return Ok(Box::new(std::iter::once(Ok(Frame::new(shrunk)))));
+            }
+            let img = ImageReader::with_format(input, format).decode().map_err(|e| decode_error(e, Some(format)))?;
+            Ok(Box::new(std::iter::once(Ok(Frame::new(tonemap::to_rgba8(img, tonemap))))))
+        }
+    }
+}
+
+/// Decodes frames up to and including `index`, returning that one.
+pub fn load_frame(path: &Path, index: usize, tonemap: Tonemap, icon: IconEntry, shrink: Option<(u32, u32)>) -> Result<Frame> {
+    let mut count = 0;
+    for frame in frames(path, tonemap, icon, shrink)? {
+        let frame = frame?;
+        if count == index {
+            return Ok(frame);
+        }
+        count += 1;
+    }
+    Err(JivError::InvalidArg(format!("Frame {} is out of range: the image has {} frame(s)", index, count)))
+}
+
+/// Opens a still image, or the first frame of an animation. The format is
+/// sniffed from the contents, falling back to the extension. HDR images
+/// come back as is, in linear floating point; see [`tonemap::to_rgba8`].
+/// Icons and cursors give their largest size.
+pub fn open_image(path: &Path) -> Result<DynamicImage> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+    match reader.format() {
+        format @ (Some(ImageFormat::Ico) | None) => decode_icon(reader.into_inner(), format, IconEntry::Largest),
+        format => reader.decode().map_err(|e| decode_error(e, format)),
+    }
+}
+
+/// Decodes the size `icon` picks if `input` is an icon or cursor file, and
+/// otherwise decodes it as `format`. Cursors aren't recognized at all, so
+/// this is where anything of unknown format ends up.
+fn decode_icon(mut input: impl Read, format: Option<ImageFormat>, icon: IconEntry) -> Result<DynamicImage> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    let (bytes, format) = match icon::select(&bytes, icon) {
+        Some(single) => (single, Some(ImageFormat::Ico)),
+        None => (bytes, format),
+    };
+    let mut reader = ImageReader::new(Cursor::new(bytes));
+    if let Some(format) = format {
+        reader.set_format(format);
+    }
+    reader.decode().map_err(|e| decode_error(e, format))
+}
+
+fn decode_error(e: ImageError, format: Option<ImageFormat>) -> JivError {
+    match e {
+        ImageError::Unsupported(_) => JivError::UnsupportedFormat { format, source: e },
+        e => JivError::Decode(e),
+    }
+}
+
+/// The formats this build can decode, by every extension they're known by.
+pub fn supported_formats() -> String {
+    // `image` reports AVIF as readable whenever its encoder is built, but
+    // decoding needs our `avif` feature
+    ImageFormat::all()
+        .filter(|f| f.reading_enabled() && (*f != ImageFormat::Avif || cfg!(feature = "avif")))
+        .flat_map(|f| f.extensions_str().iter().copied())
+        // Cursors decode along with icons
+        .chain(ImageFormat::Ico.reading_enabled().then_some("cur"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The image files in `dir` (by extension), in natural order.
+pub fn list_images(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let readable = ImageFormat::from_path(&path).is_ok_and(|f| f.reading_enabled()) || icon::is_cursor_path(&path);
+        if path.is_file() && readable {
+            paths.push(path);
+        }
+    }
+    paths.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    Ok(paths)
+}
+
+/// The order multiple inputs, and the images of a directory, are shown in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortFiles {
+    /// By name, with numbers compared by value: `frame2` before `frame10`
+    #[default]
+    Natural,
+    /// By name, character by character: `frame10` before `frame2`
+    Name,
+    /// Oldest modification time first
+    Mtime,
+    /// As given on the command line (directories still list naturally)
+    None,
+}
+
+impl SortFiles {
+    /// Sorts `paths` in this order. The sort is stable, so paths that
+    /// compare equal keep their order; files whose time can't be read sort
+    /// first by mtime.
+    pub fn sort(self, paths: &mut [PathBuf]) {
+        match self {
+            SortFiles::Natural => paths.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy())),
+            SortFiles::Name => paths.sort(),
+            SortFiles::Mtime => paths.sort_by_cached_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok()),
+            SortFiles::None => {}
+        }
+    }
+}
+
+/// Compares strings with runs of digits ordered by value, so `img2` sorts
+/// before `img10`. Everything else compares by character.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ordering = match (a.peek(), b.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                // Longer numbers are bigger once leading zeros are gone
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                x_value.len().cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()))
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.cmp(y);
+                a.next();
+                b.next();
+                ordering
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+fn take_number(chars: &mut Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits
+}
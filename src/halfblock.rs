@@ -0,0 +1,56 @@
+use crate::cell::{Cell, Grid};
+use crate::{Mode, RenderOptions, Renderer};
+use image::DynamicImage;
+
+/// Upper half blocks, as a [`Renderer`]; see [`render`].
+pub struct HalfBlock;
+
+impl Renderer for HalfBlock {
+    fn cell_size(&self) -> (u32, u32) {
+        (1, 2)
+    }
+
+    fn render(&self, img: &DynamicImage, opts: &RenderOptions) -> Grid {
+        render(img, opts)
+    }
+}
+
+/// Renders an image with upper half blocks: the top pixel of each cell is
+/// drawn in the foreground color and the bottom pixel in the background.
+pub fn render(img: &DynamicImage, opts: &RenderOptions) -> Grid {
+    let mut resized = crate::fit_image(img, opts, Mode::HalfBlock);
+
+    if opts.sharpen_after_resize() {
+        resized = image::imageops::filter3x3(&resized, &crate::SHARPEN);
+    }
+
+    let (width, height) = resized.dimensions();
+    let mut grid = Grid::new();
+
+    for y in (0..height).step_by(2) {
+        let mut row = Vec::new();
+
+        for x in 0..width {
+            if crate::key::keyed_cell(&resized, opts, (x, y), (1, 2)) {
+                row.push(Cell::blank());
+                continue;
+            }
+
+            let top = resized.get_pixel(x, y);
+            // An odd last row has no bottom pixel; leave the terminal background
+            let bg = (y + 1 < height).then(|| {
+                let bottom = resized.get_pixel(x, y + 1);
+                (bottom[0], bottom[1], bottom[2])
+            });
+
+            row.push(Cell {
+                glyph: '▀',
+                fg: Some((top[0], top[1], top[2])),
+                bg,
+                bold: false,
+            });
+        }
+        grid.push(row);
+    }
+    grid
+}
@@ -0,0 +1,48 @@
+use crate::braille::BRAILLE_COORDS;
+use crate::btc::{render_btc, Blocks};
+use crate::cell::Grid;
+use crate::octant::BRAILLE;
+use crate::quadrant::QUADRANTS;
+use crate::{Mode, RenderOptions, Renderer};
+use image::DynamicImage;
+
+/// The quadrant each of the braille sub-pixels in [`BRAILLE_COORDS`] falls
+/// in: the top two rows of dots make the upper blocks, the bottom two the
+/// lower.
+const QUADRANT_BITS: [u32; 8] = {
+    let mut bits = [0; 8];
+    let mut i = 0;
+    while i < bits.len() {
+        let (dx, dy, _) = BRAILLE_COORDS[i];
+        bits[i] = 1 << (dx + dy / 2 * 2);
+        i += 1;
+    }
+    bits
+};
+
+/// How much a cell's luma can spread (as a standard deviation) and still
+/// be drawn in blocks; busier cells get braille dots.
+const DETAIL_SPREAD: f32 = 0.1;
+
+/// Quadrant blocks with braille dots for detail, as a [`Renderer`]; see
+/// [`render`].
+pub struct Hybrid;
+
+impl Renderer for Hybrid {
+    fn cell_size(&self) -> (u32, u32) {
+        (2, 4)
+    }
+
+    fn render(&self, img: &DynamicImage, opts: &RenderOptions) -> Grid {
+        render(img, opts)
+    }
+}
+
+/// Renders an image with BTC colors, choosing the glyphs cell by cell:
+/// flat cells are quadrant blocks, which fill the cell with solid color,
+/// while cells busy with fine detail are braille dots (as in the octant
+/// mode), which resolve it at twice the rows.
+pub fn render(img: &DynamicImage, opts: &RenderOptions) -> Grid {
+    let blocks = Blocks { bits: &QUADRANT_BITS, glyphs: &QUADRANTS, spread: DETAIL_SPREAD };
+    render_btc(img, opts, Mode::Hybrid, &BRAILLE_COORDS, &BRAILLE, 0.5, Some(&blocks))
+}
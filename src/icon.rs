@@ -0,0 +1,110 @@
+use std::path::Path;
+
+/// Length of an icon file's header, before its directory.
+const HEADER_LEN: usize = 6;
+
+/// Length of each directory entry.
+const ENTRY_LEN: usize = 16;
+
+/// Which image of an icon (.ico) or cursor (.cur) file gets decoded, as
+/// these hold the same picture at several sizes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IconEntry {
+    /// The biggest one
+    #[default]
+    Largest,
+    /// The one closest to this many pixels across, the bigger on a tie
+    Nearest(u32),
+    /// The smallest one that fills a `width` x `height` box without being
+    /// scaled up, or the biggest if none does
+    Fit(u32, u32),
+}
+
+/// One image in an icon file's directory.
+#[derive(Clone, Copy)]
+struct Entry {
+    width: u32,
+    height: u32,
+    bit_count: u16,
+    /// Where its directory entry starts
+    at: usize,
+    /// Where its image data is in the file, and how long it is
+    offset: usize,
+    len: usize,
+}
+
+/// If `bytes` is an icon or cursor file, a copy holding just the image
+/// `pick` chooses, as an .ico the decoder opens directly. `None` for
+/// anything else, including files whose directory doesn't add up, so a
+/// TGA that happens to start the same way is left alone.
+pub(crate) fn select(bytes: &[u8], pick: IconEntry) -> Option<Vec<u8>> {
+    let entries = directory(bytes)?;
+    let entry = match pick {
+        IconEntry::Largest => largest(&entries)?,
+        IconEntry::Nearest(size) => entries
+            .iter()
+            .copied()
+            .min_by_key(|e| (e.width.abs_diff(size), std::cmp::Reverse((e.width, e.bit_count))))?,
+        IconEntry::Fit(width, height) => entries
+            .iter()
+            .copied()
+            // Fitting keeps the aspect, so one side reaching the box is enough
+            .filter(|e| e.width >= width || e.height >= height)
+            .min_by_key(|e| (e.width * e.height, std::cmp::Reverse(e.bit_count)))
+            .or_else(|| largest(&entries))?,
+    };
+
+    // A one-entry icon: the header with a count of one, the chosen entry
+    // pointing just past it, then the image. Cursors keep their hotspot
+    // where icons keep planes and depth, which the decoder doesn't need.
+    let mut single = vec![0, 0, 1, 0, 1, 0];
+    single.extend_from_slice(&bytes[entry.at..entry.at + 12]);
+    single[HEADER_LEN + 4..HEADER_LEN + 8].copy_from_slice(&[1, 0, 0, 0]);
+    single.extend_from_slice(&((HEADER_LEN + ENTRY_LEN) as u32).to_le_bytes());
+    single.extend_from_slice(&bytes[entry.offset..entry.offset + entry.len]);
+    Some(single)
+}
+
+/// Whether `path` is named like a cursor file, which image formats don't
+/// know by extension.
+pub(crate) fn is_cursor_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("cur"))
+}
+
+fn largest(entries: &[Entry]) -> Option<Entry> {
+    entries.iter().copied().max_by_key(|e| (e.width * e.height, e.bit_count))
+}
+
+/// The entries of an icon or cursor file's directory, if it is one: a
+/// header of type 1 (icon) or 2 (cursor) with at least one entry, each
+/// pointing at data inside the file past the directory.
+fn directory(bytes: &[u8]) -> Option<Vec<Entry>> {
+    let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+    let u32_at = |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+    if bytes.len() < HEADER_LEN || u16_at(0) != 0 || !matches!(u16_at(2), 1 | 2) {
+        return None;
+    }
+    let count = u16_at(4) as usize;
+    let data_start = HEADER_LEN + count * ENTRY_LEN;
+    if count == 0 || bytes.len() < data_start {
+        return None;
+    }
+
+    (0..count)
+        .map(|i| {
+            let at = HEADER_LEN + i * ENTRY_LEN;
+            // Sizes are a byte each, with 0 standing for 256
+            let size = |b: u8| if b == 0 { 256 } else { b as u32 };
+            let (offset, len) = (u32_at(at + 12) as usize, u32_at(at + 8) as usize);
+            let inside = bytes[at + 3] == 0 && len > 0 && offset >= data_start && offset.checked_add(len)? <= bytes.len();
+            inside.then_some(Entry {
+                width: size(bytes[at]),
+                height: size(bytes[at + 1]),
+                bit_count: u16_at(at + 6),
+                at,
+                offset,
+                len,
+            })
+        })
+        .collect()
+}
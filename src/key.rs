@@ -0,0 +1,61 @@
+use crate::RenderOptions;
+use image::RgbaImage;
+use std::str::FromStr;
+
+/// A color to key out, with how far (per channel, 0-255) a pixel may stray
+/// from it and still match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyColor {
+    pub color: (u8, u8, u8),
+    pub tolerance: u8,
+}
+
+impl KeyColor {
+    pub fn matches(&self, (r, g, b): (u8, u8, u8)) -> bool {
+        let (kr, kg, kb) = self.color;
+        r.abs_diff(kr) <= self.tolerance && g.abs_diff(kg) <= self.tolerance && b.abs_diff(kb) <= self.tolerance
+    }
+}
+
+impl FromStr for KeyColor {
+    type Err = String;
+
+    /// Parses `#rrggbb`, optionally followed by `±TOL` (or `+-TOL`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (color, tolerance) = match s.split_once('±').or_else(|| s.split_once("+-")) {
+            Some((color, tolerance)) => {
+                let tolerance = tolerance.trim().parse().map_err(|_| format!("tolerance must be 0-255: {}", tolerance))?;
+                (color, tolerance)
+            }
+            None => (s, 0),
+        };
+        Ok(KeyColor { color: crate::color::parse_hex(color.trim())?, tolerance })
+    }
+}
+
+/// Whether the `width` x `height` cell at (`x`, `y`) of `img` is keyed out,
+/// and so left blank: true when most of its pixels match `opts.keep_color`,
+/// or when its average luma (0-255) is below `opts.luma_key`. Keyed cells
+/// are skipped entirely, so they neither color their neighbors through
+/// averaging nor spread dithering error.
+pub(crate) fn keyed_cell(img: &RgbaImage, opts: &RenderOptions, (x, y): (u32, u32), (width, height): (u32, u32)) -> bool {
+    if opts.keep_color.is_none() && opts.luma_key.is_none() {
+        return false;
+    }
+
+    let (mut matching, mut luma_sum, mut total) = (0, 0.0, 0);
+    for py in y..(y + height).min(img.height()) {
+        for px in x..(x + width).min(img.width()) {
+            let p = img.get_pixel(px, py);
+            if opts.keep_color.is_some_and(|key| key.matches((p[0], p[1], p[2]))) {
+                matching += 1;
+            }
+            luma_sum += opts.luma.luma(p[0] as f32, p[1] as f32, p[2] as f32);
+            total += 1;
+        }
+    }
+    if total == 0 {
+        return false;
+    }
+    2 * matching > total || opts.luma_key.is_some_and(|level| luma_sum / (total as f32) < level as f32)
+}
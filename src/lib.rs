@@ -0,0 +1,493 @@
+//! Rendering pipeline shared by the jiv2 binaries: each mode turns an image
+//! into a grid of colored glyph cells, which is then printed as ANSI or
+//! rasterized back into an image.
+
+pub mod ascii;
+pub mod blocks;
+pub mod braille;
+mod btc;
+pub mod cell;
+pub mod cli;
+pub mod color;
+pub mod dither;
+pub mod eighths;
+pub mod error;
+#[cfg(feature = "http")]
+pub mod fetch;
+pub mod frames;
+pub mod halfblock;
+pub mod hybrid;
+pub mod icon;
+pub mod key;
+pub mod mode;
+pub mod octant;
+pub mod palette;
+pub mod pager;
+pub mod probe;
+pub mod quadrant;
+pub mod raster;
+pub mod sextant;
+pub mod shrink;
+#[cfg(feature = "simd")]
+mod simd;
+pub mod stats;
+pub mod theme;
+pub mod tonemap;
+
+pub use error::JivError;
+pub use mode::{render_with, GlyphSupport, Mode, Renderer};
+
+use crossterm::terminal;
+use image::{imageops::FilterType, DynamicImage, RgbaImage};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Rows left free below a terminal-high render by default, for the prompt
+/// and a line of spacing.
+pub const RESERVED_LINES: u32 = 2;
+
+/// 3x3 sharpening kernel applied by the edges option.
+pub(crate) const SHARPEN: [f32; 9] = [
+    0.0, -1.0, 0.0,
+    -1.0, 5.0, -1.0,
+    0.0, -1.0, 0.0
+];
+
+/// Renders `img` in `opts.mode` and streams the ANSI bytes into `w`.
+pub fn render_to_writer<W: Write>(img: &DynamicImage, opts: &RenderOptions, w: W) -> io::Result<()> {
+    cell::write_ansi(&opts.mode.render(img, opts), w)
+}
+
+/// Renders `img` in `opts.mode` and calls `visit(row, col, cell)` with each
+/// character cell's glyph and colors, top to bottom and left to right, for
+/// embedders that build their own output instead of parsing the ANSI.
+pub fn render_cells(img: &DynamicImage, opts: &RenderOptions, visit: impl FnMut(usize, usize, &cell::Cell)) {
+    cell::visit_cells(&opts.mode.render(img, opts), visit)
+}
+
+/// Renders `img` in `opts.mode` into an ANSI string.
+pub fn render_to_string(img: &DynamicImage, opts: &RenderOptions) -> String {
+    let mut buf = Vec::new();
+    render_to_writer(img, opts, &mut buf).expect("writing to a Vec cannot fail");
+    String::from_utf8(buf).expect("ANSI output is valid UTF-8")
+}
+
+/// Options shared by every renderer.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    /// Glyph set to render with
+    pub mode: Mode,
+    /// Enable edge detection enhancement (sharpening)
+    pub edges: bool,
+    /// Whether `edges` sharpens the full-resolution image or the resized one
+    pub sharpen_order: SharpenOrder,
+    /// Channel the braille dots are dithered from; other modes ignore it
+    pub channel: braille::Channel,
+    /// Weights luma is taken with, wherever a mode works from brightness
+    pub luma: color::LumaWeights,
+    /// How quadrant and sextant cells pick their glyph; other modes ignore it
+    pub glyph_match: GlyphMatch,
+    /// Luma range (0-1) below which a cell of the BTC modes (octant,
+    /// sextant, quadrant) is drawn as a space in its average color, instead
+    /// of a full block or a split driven by noise; other modes ignore it
+    pub min_contrast: Option<f32>,
+    /// Recolor each cell by its luma along a gradient from the first
+    /// (shadows) to the second (highlights) sRGB color; braille and the
+    /// block modes only
+    pub duotone: Option<(color::Rgb8, color::Rgb8)>,
+    /// Quantize luma into this many bands before the glyph shapes (and
+    /// duotone colors) are picked, for a screen-print look
+    pub posterize: Option<u32>,
+    /// Color every braille dot this sRGB color instead of the image's own;
+    /// other modes ignore it
+    pub tint: Option<(u8, u8, u8)>,
+    /// Level braille dots turn on at; other modes ignore it
+    pub threshold: braille::Threshold,
+    /// Dither braille dots with an 8x8 Bayer pattern around `threshold`
+    /// instead of error diffusion, for repeatable, tileable output; other
+    /// modes ignore it
+    pub ordered: bool,
+    /// Exponent of the curve linear luma goes through before braille dots
+    /// are dithered from it; by default it follows each cell's contrast,
+    /// from 0.2 in flat cells to 0.5 in contrasty ones, to balance the
+    /// brightening of the dot colors. Other modes ignore it
+    pub luma_gamma: Option<f32>,
+    /// Width of the output; the terminal width when `None`
+    pub width: Option<Dimension>,
+    /// Height of the output; the terminal height (minus `reserve_lines`) when `None`
+    pub height: Option<Dimension>,
+    /// Upper bound on the width; only ever shrinks the output
+    pub max_width: Option<Dimension>,
+    /// Upper bound on the height; only ever shrinks the output
+    pub max_height: Option<Dimension>,
+    /// Rows left free below the output when its height comes from the
+    /// terminal; [`RESERVED_LINES`] when `None`
+    pub reserve_lines: Option<u32>,
+    /// Composite transparent pixels over this sRGB color; otherwise their
+    /// color channels are used as is
+    pub background: Option<(u8, u8, u8)>,
+    /// Composite transparent pixels over this checkerboard instead
+    pub checker: Option<color::Checker>,
+    /// How the pixels behind each cell color are averaged
+    pub color_average: color::ColorAverage,
+    /// Weight each pixel by its alpha when averaging cell colors, so the
+    /// RGB of transparent pixels doesn't tint what's visible
+    pub alpha_weight: bool,
+    /// Invert the colors (after compositing)
+    pub invert: bool,
+    /// Leave cells mostly of this color blank, so the terminal's own
+    /// background shows through
+    pub keep_color: Option<key::KeyColor>,
+    /// Round every drawn color to this many bits per channel (1-8), for a
+    /// retro limited palette
+    pub bits: Option<u8>,
+    /// Replace every drawn color with its nearest entry in this palette
+    pub palette: Option<palette::Palette>,
+    /// How the nearest palette entry is picked
+    pub color_distance: color::ColorDistance,
+    /// Share (0-1) of each color's palette error that dithering spreads
+    /// to its neighbors; all of it when unset
+    pub palette_dither_strength: Option<f32>,
+    /// Leave cells whose average luma (0-255) is below this blank, keying
+    /// out dark backgrounds
+    pub luma_key: Option<u8>,
+    /// Swap the two colors of quadrant, sextant and octant cells, drawing
+    /// the glyph as the darker part; other modes ignore it
+    pub invert_bg: bool,
+    /// Map bright pixels to dense ASCII glyphs instead of dark ones; other
+    /// modes ignore it
+    pub ascii_ramp_invert: bool,
+    /// Flip which pixels each mode's glyphs draw: braille dots, the glyph
+    /// of block cells, legacy-blocks shades and eighths bars stand for dark
+    /// pixels instead of bright ones, and the ascii ramp for bright instead
+    /// of dark. Halfblock and the legacy-blocks bars ignore it
+    pub invert_diffusion: bool,
+    /// Dithering of the ascii ramp levels, and of the colors reduced by
+    /// `bits` or `palette`
+    pub dither: dither::Dither,
+    /// What error diffusion does at the image borders (braille, octant and
+    /// dithered ascii)
+    pub edge_handling: dither::EdgeHandling,
+    /// Tile the image at its own size to fill the output instead of scaling it
+    pub repeat: bool,
+    /// Scale the image to fill the whole output, ignoring its aspect ratio
+    pub stretch: bool,
+    /// Resize in sRGB gamma space instead of linear light, which is faster
+    /// but darkens small bright details
+    pub gamma_resize: bool,
+    /// After downscaling, pull each pixel toward the brightest one it
+    /// covers, so thin highlights survive at some cost to tonal accuracy
+    pub weighted_resize: bool,
+    /// Dither braille dots at this many pixels per dot along each axis, then
+    /// light each dot by how much of its block came out on; 0 and 1 both
+    /// dither one pixel per dot
+    pub supersample: u32,
+    /// Blend the dots of braille cells along edges toward the background
+    /// by how few of them are on; other modes ignore it
+    pub antialias_edges: bool,
+    /// Draw braille dots without the bold attribute, which is there to
+    /// intensify their colors but which some terminals render as a brighter
+    /// color or a heavier font that distorts the glyphs; other modes never
+    /// use bold
+    pub no_bold: bool,
+    /// Diffuse octant color error along alternating directions row by row
+    /// instead of always left to right; other modes ignore it
+    pub serpentine: bool,
+    /// Shade the background of every other cell, to show where the cell
+    /// boundaries fall
+    pub cell_debug: bool,
+    /// Width over height of the source's pixels, for images that aren't
+    /// meant to be shown with square ones
+    pub pixel_aspect: Option<f32>,
+    /// The terminal draws each glyph two columns wide: the output gets half
+    /// as many cells across, each twice as wide on screen
+    pub double_width: bool,
+}
+
+impl RenderOptions {
+    /// Whether renderers should sharpen the image they get from `fit_image`.
+    pub(crate) fn sharpen_after_resize(&self) -> bool {
+        self.edges && self.sharpen_order == SharpenOrder::After
+    }
+
+    /// A cell color as drawn: mapped onto the duotone gradient by its
+    /// (posterized) luma when there is one, otherwise unchanged.
+    pub(crate) fn cell_color(&self, (r, g, b): (u8, u8, u8)) -> (u8, u8, u8) {
+        match self.duotone {
+            Some((dark, light)) => {
+                color::duotone(dark, light, self.posterize(self.luma.luma(r as f32, g as f32, b as f32) / 255.0))
+            }
+            None => (r, g, b),
+        }
+    }
+
+    /// The average of a cell's (or color group's) linear `colors`, as
+    /// `color_average` takes it, counting `count` pixels. With
+    /// `alpha_weight` each counts by its entry in `alphas` instead, and
+    /// pixels that are all transparent have no color at all.
+    pub(crate) fn cell_mean(&self, colors: &[(f32, f32, f32)], alphas: &[f32], count: f32) -> Option<(f32, f32, f32)> {
+        if self.alpha_weight {
+            self.color_average.weighted_mean(colors, alphas)
+        } else {
+            Some(self.color_average.mean(colors, count))
+        }
+    }
+
+    /// Snaps a 0-1 luma to the nearest of `posterize` evenly spaced bands,
+    /// from 0 to 1 inclusive; unchanged without posterizing.
+    pub(crate) fn posterize(&self, luma: f32) -> f32 {
+        match self.posterize {
+            Some(bands) if bands >= 2 => {
+                let band = (luma.clamp(0.0, 1.0) * bands as f32).floor().min((bands - 1) as f32);
+                band / (bands - 1) as f32
+            }
+            _ => luma,
+        }
+    }
+}
+
+/// When the edges option sharpens, relative to the resize.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SharpenOrder {
+    /// On the full-resolution image: keeps more fine detail, but costs time
+    /// proportional to the input's size
+    Before,
+    /// On the resized image: cheap, since it's only as big as the output
+    #[default]
+    After,
+}
+
+/// How the block modes pick which sub-pixels of a cell are lit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum GlyphMatch {
+    /// Light the sub-pixels at or above the cell's average luma
+    #[default]
+    Threshold,
+    /// Try every glyph and keep the one whose two colors fit the sub-pixels
+    /// best: sharper structure for up to 64x the work per cell
+    Exhaustive,
+}
+
+/// An output dimension, either absolute or relative to the terminal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dimension {
+    /// A fixed number of character cells
+    Cells(u32),
+    /// A percentage of the detected terminal dimension
+    Percent(f32),
+}
+
+impl Dimension {
+    /// Resolves to a cell count given the terminal's size along this axis.
+    pub fn cells(self, terminal: u32) -> u32 {
+        match self {
+            Dimension::Cells(n) => n,
+            Dimension::Percent(p) => ((terminal as f32 * p / 100.0).round() as u32).max(1),
+        }
+    }
+}
+
+impl FromStr for Dimension {
+    type Err = String;
+
+    /// Parses `80` as 80 cells or `50%` as half the terminal.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(percent) = s.strip_suffix('%') {
+            let p: f32 = percent.trim().parse().map_err(|_| format!("invalid percentage: {}", s))?;
+            if !(p > 0.0 && p <= 100.0) {
+                return Err(format!("percentage must be in (0, 100]: {}", s));
+            }
+            Ok(Dimension::Percent(p))
+        } else {
+            let n: u32 = s.parse().map_err(|_| format!("expected a cell count or a percentage: {}", s))?;
+            if n == 0 {
+                return Err("size must be at least 1 cell".into());
+            }
+            Ok(Dimension::Cells(n))
+        }
+    }
+}
+
+/// The terminal's size in cells, as (columns, rows).
+pub fn terminal_size() -> Result<(u32, u32), JivError> {
+    let (columns, rows) = terminal::size().map_err(JivError::TerminalSize)?;
+    Ok((columns as u32, rows as u32))
+}
+
+/// Size of the box the image is fit into, in virtual pixels: 2 per column
+/// and 4 per row, before any mode-specific vertical squash.
+pub(crate) fn target_size(opts: &RenderOptions) -> (u32, u32) {
+    let (term_w, term_h) = terminal_size().unwrap_or((80, 24));
+
+    let mut columns = opts.width.map_or(term_w, |w| w.cells(term_w));
+    let reserved = opts.reserve_lines.unwrap_or(RESERVED_LINES);
+    let mut rows = opts.height.map_or(term_h.saturating_sub(reserved).max(1), |h| h.cells(term_h));
+
+    if let Some(max) = opts.max_width {
+        columns = columns.min(max.cells(term_w));
+    }
+    if let Some(max) = opts.max_height {
+        rows = rows.min(max.cells(term_h));
+    }
+    if opts.double_width {
+        columns = (columns / 2).max(1);
+    }
+
+    (columns * 2, rows * 4)
+}
+
+/// Resizes `img` to fit the output, with one pixel per sub-cell of `mode`.
+///
+/// The fit is done on square pixels (2x4 per 1:2 terminal cell); modes whose
+/// sub-cells aren't square then get their pixels squashed to match. Unless
+/// `opts.gamma_resize` is set, both resizes average in linear light. With
+/// `opts.repeat` the image is tiled at its own size instead of scaled, and with
+/// `opts.stretch` it is scaled to fill the box regardless of aspect. Non-square
+/// source pixels (`opts.pixel_aspect`) are stretched square first, as is
+/// everything for double-width cells (`opts.double_width`). The
+/// background and invert options are applied here, so every mode gets them,
+/// as is sharpening when it's ordered before the resize.
+pub(crate) fn fit_image(img: &DynamicImage, opts: &RenderOptions, mode: Mode) -> RgbaImage {
+    fit_image_scaled(img, opts, mode, 1)
+}
+
+/// Like [`fit_image`], but with `scale` x `scale` pixels per sub-cell.
+pub(crate) fn fit_image_scaled(img: &DynamicImage, opts: &RenderOptions, mode: Mode, scale: u32) -> RgbaImage {
+    let (target_width, target_height) = target_size(opts);
+    let (target_width, target_height) = (target_width * scale, target_height * scale);
+    let (cell_w, cell_h) = mode.cell_size();
+
+    let sharpened;
+    let img = if opts.edges && opts.sharpen_order == SharpenOrder::Before {
+        sharpened = DynamicImage::ImageRgba8(image::imageops::filter3x3(img, &SHARPEN));
+        &sharpened
+    } else {
+        img
+    };
+
+    let stretched;
+    // Double-width cells are shown twice as wide, so the image is narrowed
+    // to match, like pixels half as wide as they are tall
+    let aspect = opts.pixel_aspect.unwrap_or(1.0) / if opts.double_width { 2.0 } else { 1.0 };
+    let img = if aspect != 1.0 {
+        stretched = stretch_pixels(img, aspect, opts.gamma_resize);
+        &stretched
+    } else {
+        img
+    };
+
+    // Tiling fills the box exactly, so the fit below leaves it as is
+    let tiled;
+    let img = if opts.repeat && img.width() > 0 && img.height() > 0 {
+        let source = img.to_rgba8();
+        tiled = DynamicImage::ImageRgba8(RgbaImage::from_fn(target_width, target_height, |x, y| {
+            *source.get_pixel(x % source.width(), y % source.height())
+        }));
+        &tiled
+    } else {
+        img
+    };
+
+    let fit = |img: &DynamicImage| {
+        let resized = if opts.stretch {
+            img.resize_exact(target_width, target_height, FilterType::Lanczos3)
+        } else {
+            img.resize(target_width, target_height, FilterType::Lanczos3)
+        };
+        if (cell_w, cell_h) == (2, 4) {
+            return resized;
+        }
+        resized.resize_exact(
+            (resized.width() * cell_w / 2).max(1),
+            (resized.height() * cell_h / 4).max(1),
+            FilterType::Lanczos3,
+        )
+    };
+
+    let mut fitted = if opts.gamma_resize {
+        fit(img).to_rgba8()
+    } else {
+        let linear = DynamicImage::ImageRgba32F(color::image_to_linear(img));
+        color::linear_image_to_srgb8(&fit(&linear).into_rgba32f())
+    };
+    if opts.weighted_resize {
+        keep_highlights(img, &mut fitted, opts.luma);
+    }
+
+    if let Some(background) = opts.background {
+        color::composite_over(&mut fitted, background);
+    } else if let Some(checker) = opts.checker {
+        let square = (checker.size * cell_w * scale, checker.size * cell_h * scale / 2);
+        color::composite_over_checker(&mut fitted, checker, square);
+    }
+    if opts.invert {
+        image::imageops::invert(&mut fitted);
+    }
+    fitted
+}
+
+/// Pulls each pixel of `fitted`, a downscale of `img`, toward the brightest
+/// source pixel it covers, by how far that one's luma stands out above the
+/// average there: a lone highlight such as a star or a wire keeps nearly
+/// its full brightness, while texture only brightens a little. Nothing
+/// changes where the image was scaled up.
+fn keep_highlights(img: &DynamicImage, fitted: &mut RgbaImage, luma: color::LumaWeights) {
+    let source = img.to_rgba8();
+    let (source_width, source_height) = source.dimensions();
+    let (width, height) = fitted.dimensions();
+    if source_width <= width && source_height <= height {
+        return;
+    }
+
+    // The source pixels output pixel `i` of `to` covers, out of `from`
+    let span = |i: u32, from: u32, to: u32| {
+        let start = (i as u64 * from as u64 / to as u64) as u32;
+        let end = ((i as u64 + 1) * from as u64).div_ceil(to as u64) as u32;
+        start..end.clamp(start + 1, from)
+    };
+    let brightness = |p: &image::Rgba<u8>| luma.luma(p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0) * p[3] as f32 / 255.0;
+
+    for y in 0..height {
+        let rows = span(y, source_height, height);
+        for x in 0..width {
+            let (mut brightest, mut peak, mut sum, mut count) = (None, 0.0, 0.0, 0.0);
+            for sy in rows.clone() {
+                for sx in span(x, source_width, width) {
+                    let p = source.get_pixel(sx, sy);
+                    let b = brightness(p);
+                    if brightest.is_none() || b > peak {
+                        (brightest, peak) = (Some(*p), b);
+                    }
+                    sum += b;
+                    count += 1.0;
+                }
+            }
+            let mean = sum / count;
+            let Some(brightest) = brightest.filter(|_| peak > mean && mean < 1.0) else {
+                continue;
+            };
+            let share = (peak - mean) / (1.0 - mean);
+            let pixel = fitted.get_pixel_mut(x, y);
+            for c in 0..4 {
+                pixel[c] = (pixel[c] as f32 + (brightest[c] as f32 - pixel[c] as f32) * share).round() as u8;
+            }
+        }
+    }
+}
+
+/// Resizes an image whose pixels are `aspect` times as wide as they are tall
+/// so its pixels are square, growing whichever side is short so no detail
+/// is lost.
+fn stretch_pixels(img: &DynamicImage, aspect: f32, gamma_resize: bool) -> DynamicImage {
+    let (width, height) = (img.width() as f32, img.height() as f32);
+    let (width, height) = if aspect > 1.0 { (width * aspect, height) } else { (width, height / aspect) };
+    let (width, height) = ((width.round() as u32).max(1), (height.round() as u32).max(1));
+
+    if gamma_resize {
+        img.resize_exact(width, height, FilterType::Lanczos3)
+    } else {
+        let linear = DynamicImage::ImageRgba32F(color::image_to_linear(img));
+        let resized = linear.resize_exact(width, height, FilterType::Lanczos3).into_rgba32f();
+        DynamicImage::ImageRgba8(color::linear_image_to_srgb8(&resized))
+    }
+}
@@ -0,0 +1,203 @@
+use crate::cell::{self, Grid};
+use crate::{ascii, blocks, braille, dither, eighths, halfblock, hybrid, octant, quadrant, sextant, RenderOptions};
+use image::DynamicImage;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+/// Sub-pixel columns a render should have across for fine detail; a mode's
+/// [`Mode::min_width`] is the cells it takes to get there.
+const MIN_DETAIL_COLUMNS: u32 = 80;
+
+/// The glyph set an image is rendered with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Mode {
+    /// Braille dots (2x4) dithered from luma, one color per cell
+    Braille,
+    /// Quadrant blocks with BTC colors, switching to braille dots (2x4)
+    /// in cells busy with fine detail
+    Hybrid,
+    /// Braille dots (2x4) with a true-color BTC foreground and background
+    Octant,
+    /// Legacy Computing sextants (2x3) with BTC colors
+    #[default]
+    Sextant,
+    /// Quadrant blocks (2x2) with BTC colors
+    Quadrant,
+    /// Plain ASCII density ramp, no color
+    Ascii,
+    /// Upper half blocks (1x2), one pixel in each color
+    #[value(name = "halfblock")]
+    HalfBlock,
+    /// Eighth-block bars and shades, one per cell, following each cell's
+    /// profile down or across it
+    LegacyBlocks,
+    /// Lower eighth blocks as tall as each cell's luma, no color
+    Eighths,
+}
+
+impl Mode {
+    /// Sub-pixels per character cell, as (columns, rows).
+    pub fn cell_size(self) -> (u32, u32) {
+        self.renderer().cell_size()
+    }
+
+    /// The [`Renderer`] that draws this mode.
+    pub fn renderer(self) -> &'static dyn Renderer {
+        match self {
+            Mode::Braille => &braille::Braille,
+            Mode::Hybrid => &hybrid::Hybrid,
+            Mode::Octant => &octant::Octant,
+            Mode::Sextant => &sextant::Sextant,
+            Mode::Quadrant => &quadrant::Quadrant,
+            Mode::Ascii => &ascii::Ascii,
+            Mode::HalfBlock => &halfblock::HalfBlock,
+            Mode::LegacyBlocks => &blocks::LegacyBlocks,
+            Mode::Eighths => &eighths::Eighths,
+        }
+    }
+
+    /// The fewest terminal columns this mode needs for fine detail: 40 for
+    /// the modes with two sub-pixels across each cell, 80 for those with one.
+    pub fn min_width(self) -> u32 {
+        MIN_DETAIL_COLUMNS.div_ceil(self.cell_size().0)
+    }
+
+    /// A mode `support` can show that gets more detail than this one into
+    /// `columns` columns, when that's short of this one's [`Mode::min_width`]:
+    /// of those with more sub-pixels across each cell, the one with the most
+    /// per cell. `None` when this mode is wide enough, or nothing does better.
+    pub fn suggest_for_width(self, columns: u32, support: GlyphSupport) -> Option<Mode> {
+        if columns >= self.min_width() {
+            return None;
+        }
+        let (across, _) = self.cell_size();
+        <Mode as clap::ValueEnum>::value_variants()
+            .iter()
+            .copied()
+            .filter(|&mode| mode.downgrade(support) == mode && mode.cell_size().0 > across)
+            .max_by_key(|&mode| mode.cell_size().0 * mode.cell_size().1)
+    }
+
+    /// A glyph typical of this mode, to measure how wide the terminal draws
+    /// its glyphs.
+    pub fn sample_glyph(self) -> char {
+        match self {
+            Mode::Braille | Mode::Hybrid | Mode::Octant => '⣿',
+            Mode::Sextant => '\u{1fb0b}',
+            Mode::Quadrant => '▚',
+            Mode::Ascii => '@',
+            Mode::HalfBlock => '▀',
+            Mode::LegacyBlocks => '▃',
+            Mode::Eighths => '▅',
+        }
+    }
+
+    /// The richest mode at most as demanding as this one that `support` can
+    /// show: sextants, braille and the eighth blocks drop to quadrants on
+    /// block-only consoles, and everything drops to the ASCII ramp where
+    /// only ASCII is safe.
+    pub fn downgrade(self, support: GlyphSupport) -> Mode {
+        match (support, self) {
+            (GlyphSupport::Full, mode) => mode,
+            (GlyphSupport::Blocks, Mode::Braille | Mode::Hybrid | Mode::Octant | Mode::Sextant | Mode::LegacyBlocks | Mode::Eighths) => Mode::Quadrant,
+            (GlyphSupport::Blocks, mode) => mode,
+            (GlyphSupport::Ascii, _) => Mode::Ascii,
+        }
+    }
+
+    pub fn render(self, img: &DynamicImage, opts: &RenderOptions) -> Grid {
+        render_with(self.renderer(), img, opts)
+    }
+}
+
+/// A glyph set: how many sub-pixels each of its cells holds, and how to
+/// draw an image with it. Each [`Mode`] has one; a new glyph set only has
+/// to implement this to go through [`render_with`] like the built-in ones.
+pub trait Renderer {
+    /// Sub-pixels per character cell, as (columns, rows).
+    fn cell_size(&self) -> (u32, u32);
+
+    /// Draws `img` as a grid of cells. The bits, palette and cell debug
+    /// options are applied afterwards by [`render_with`], so renderers
+    /// can leave them be.
+    fn render(&self, img: &DynamicImage, opts: &RenderOptions) -> Grid;
+}
+
+/// Renders `img` with `renderer`, then reduces the colors to `opts.bits`
+/// or `opts.palette` and checkers the cells for `opts.cell_debug`.
+pub fn render_with(renderer: &dyn Renderer, img: &DynamicImage, opts: &RenderOptions) -> Grid {
+    let mut grid = renderer.render(img, opts);
+    if let Some(bits) = opts.bits {
+        dither::reduce_bits(&mut grid, bits, opts.dither, opts.edge_handling);
+    }
+    if let Some(palette) = &opts.palette {
+        let strength = opts.palette_dither_strength.unwrap_or(1.0);
+        dither::reduce_to_palette(&mut grid, palette, opts.color_distance, (opts.dither, strength), opts.edge_handling);
+    }
+    if opts.cell_debug {
+        cell::checker_cells(&mut grid);
+    }
+    grid
+}
+
+/// Which glyphs the terminal can be expected to draw.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlyphSupport {
+    /// Any Unicode, including Legacy Computing sextants and braille
+    Full,
+    /// Block elements only, like the Linux console's fonts
+    Blocks,
+    /// Plain ASCII
+    Ascii,
+}
+
+impl GlyphSupport {
+    /// Guesses from `$TERM` and the locale: a non-UTF-8 locale or a dumb or
+    /// VT terminal gets ASCII, the Linux and BSD consoles get blocks, and
+    /// anything else is assumed to be a modern terminal.
+    pub fn detect() -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty());
+        if let Some(locale) = locale {
+            let locale = locale.to_ascii_lowercase();
+            if !locale.contains("utf-8") && !locale.contains("utf8") {
+                return GlyphSupport::Ascii;
+            }
+        }
+
+        match env::var("TERM").as_deref() {
+            Ok("dumb") => GlyphSupport::Ascii,
+            Ok(term) if term.starts_with("vt") => GlyphSupport::Ascii,
+            Ok("linux" | "cons25" | "console") => GlyphSupport::Blocks,
+            _ => GlyphSupport::Full,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Mode::Braille => "braille",
+            Mode::Hybrid => "hybrid",
+            Mode::Octant => "octant",
+            Mode::Sextant => "sextant",
+            Mode::Quadrant => "quadrant",
+            Mode::Ascii => "ascii",
+            Mode::HalfBlock => "halfblock",
+            Mode::LegacyBlocks => "legacy-blocks",
+            Mode::Eighths => "eighths",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Mode as clap::ValueEnum>::from_str(s, true).map_err(|_| format!("unknown mode: {}", s))
+    }
+}
@@ -0,0 +1,54 @@
+use crate::cell::{write_ansi_with, AnsiOptions, Grid, OutputEncoding};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, terminal};
+use std::io::{self, BufWriter, Write};
+
+/// Shows `grid` a screenful at a time on the terminal's alternate screen,
+/// until `q` or Escape. The arrow keys (or `j`/`k`) scroll by a row, Page
+/// Up/Down (or `b`/space) by a screen, and Home/End (or `g`/`G`) jump to
+/// either end. The terminal is restored even if drawing fails.
+pub fn page(grid: &Grid, encoding: OutputEncoding) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    terminal::enable_raw_mode()?;
+    let result = execute!(out, terminal::EnterAlternateScreen, cursor::Hide).and_then(|()| browse(grid, encoding, &mut out));
+    let restored = execute!(out, cursor::Show, terminal::LeaveAlternateScreen);
+    terminal::disable_raw_mode()?;
+    result.and(restored)
+}
+
+fn browse(grid: &Grid, encoding: OutputEncoding, out: &mut impl Write) -> io::Result<()> {
+    let mut top = 0;
+    loop {
+        let screen_rows = terminal::size()?.1.max(1) as usize;
+        let last_top = grid.len().saturating_sub(screen_rows);
+        top = top.min(last_top);
+
+        // Each row is positioned, so nothing is written past the last
+        // screen row and the screen never scrolls by itself
+        let layout = AnsiOptions { at: Some((1, 1)), encoding, ..Default::default() };
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+        write_ansi_with(&grid[top..(top + screen_rows).min(grid.len())].to_vec(), &layout, &mut *out)?;
+        out.flush()?;
+
+        // Anything but a keypress or a resize leaves the screen as it is
+        let key = loop {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => break Some(key),
+                Event::Resize(..) => break None,
+                _ => {}
+            }
+        };
+        let Some(key) = key else { continue };
+        top = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => top.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Enter => top + 1,
+            KeyCode::PageUp | KeyCode::Char('b') => top.saturating_sub(screen_rows),
+            KeyCode::PageDown | KeyCode::Char(' ') => top + screen_rows,
+            KeyCode::Home | KeyCode::Char('g') => 0,
+            KeyCode::End | KeyCode::Char('G') => last_top,
+            _ => top,
+        };
+    }
+}
@@ -0,0 +1,231 @@
+use crate::color::{ColorDistance, Rgb8, linear_to_oklab, oklab_to_srgb, parse_hex, squared_distance, srgb_to_linear};
+use image::DynamicImage;
+use std::str::FromStr;
+
+/// A fixed set of colors to draw with, as read by --palette.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    colors: Vec<Rgb8>,
+    /// `colors` in linear light, for the lookups
+    linear: Vec<[f32; 3]>,
+}
+
+impl Palette {
+    /// A palette of `colors`; `None` if there are none.
+    pub fn new(colors: Vec<Rgb8>) -> Option<Self> {
+        if colors.is_empty() {
+            return None;
+        }
+        let linear = colors.iter().map(|&(r, g, b)| [r, g, b].map(|c| srgb_to_linear(c as f32 / 255.0))).collect();
+        Some(Palette { colors, linear })
+    }
+
+    pub fn colors(&self) -> &[Rgb8] {
+        &self.colors
+    }
+
+    /// A lookup of the entry closest to a linear-light color by `metric`,
+    /// which gives that entry in linear light too.
+    pub(crate) fn nearest_by(&self, metric: ColorDistance) -> impl Fn([f32; 3]) -> (Rgb8, [f32; 3]) + '_ {
+        let coordinates: Vec<_> = self.linear.iter().map(|&[r, g, b]| metric.coordinates((r, g, b))).collect();
+        move |[r, g, b]| {
+            let index = nearest(&coordinates, metric.coordinates((r, g, b)));
+            (self.colors[index], self.linear[index])
+        }
+    }
+
+    /// Reads a palette file for --palette.
+    pub fn load(path: &str) -> Result<Self, String> {
+        std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?.parse()
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    /// Parses one color per line, either `#rrggbb` (or `#rgb`) or GIMP
+    /// `.gpl` style `R G B [name]`. Blank lines, `#` comments and the `.gpl`
+    /// header lines are skipped.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut colors = Vec::new();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.trim();
+            // Decimal first: `255 0 0` would also read as the hex `#255`
+            let channels: Vec<u8> = line.split_whitespace().take(3).map_while(|v| v.parse().ok()).collect();
+            if let [r, g, b] = channels[..] {
+                colors.push((r, g, b));
+            } else if let Ok(color) = parse_hex(line.split_whitespace().next().unwrap_or("")) {
+                colors.push(color);
+            } else if !(line.is_empty() || line.starts_with('#') || line == "GIMP Palette" || line.contains(':')) {
+                return Err(format!("line {}: expected #rrggbb or R G B: {}", number + 1, line));
+            }
+        }
+        Palette::new(colors).ok_or_else(|| "palette has no colors".to_string())
+    }
+}
+
+/// Images are shrunk to fit this square before their colors are clustered;
+/// that's plenty of pixels to find the main colors.
+const SAMPLE_SIZE: u32 = 100;
+
+/// Pixels less opaque than this don't count towards any color.
+const MIN_ALPHA: u8 = 128;
+
+/// The `k` main colors of an image, most common first. Runs k-means in the
+/// space `metric` measures distance in, over a downsampled copy; each
+/// color is its cluster's mean in Oklab. Clusters that end up empty are
+/// dropped, so images with few colors give fewer than `k`.
+pub fn dominant_colors(img: &DynamicImage, k: usize, metric: ColorDistance) -> Vec<(u8, u8, u8)> {
+    let samples = opaque_pixels(&img.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE), metric);
+    let pixels: Vec<_> = samples.iter().map(|&(p, _)| p).collect();
+    if pixels.is_empty() || k == 0 {
+        return Vec::new();
+    }
+
+    // Seed with the mean, then repeatedly the pixel farthest from every seed
+    // so far. This is repeatable, and stops early once every pixel is covered.
+    let n = pixels.len() as f32;
+    let mean = pixels.iter().fold((0.0, 0.0, 0.0), |m, p| (m.0 + p.0 / n, m.1 + p.1 / n, m.2 + p.2 / n));
+    let mut centers = vec![mean];
+    let mut gaps: Vec<f32> = pixels.iter().map(|&p| squared_distance(p, mean)).collect();
+    while centers.len() < k {
+        let (farthest, &gap) = gaps.iter().enumerate().max_by(|x, y| x.1.total_cmp(y.1)).unwrap();
+        if gap <= f32::EPSILON {
+            break;
+        }
+        let seed = pixels[farthest];
+        centers.push(seed);
+        for (gap, &p) in gaps.iter_mut().zip(&pixels) {
+            *gap = gap.min(squared_distance(p, seed));
+        }
+    }
+    let k = centers.len();
+
+    let mut assignment = vec![usize::MAX; pixels.len()];
+    for _ in 0..20 {
+        let mut changed = false;
+        for (p, assigned) in pixels.iter().zip(&mut assignment) {
+            let nearest = nearest(&centers, *p);
+            changed |= nearest != *assigned;
+            *assigned = nearest;
+        }
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![(0.0, 0.0, 0.0, 0usize); k];
+        for (p, &cluster) in pixels.iter().zip(&assignment) {
+            let sum = &mut sums[cluster];
+            *sum = (sum.0 + p.0, sum.1 + p.1, sum.2 + p.2, sum.3 + 1);
+        }
+        for (center, &(l, a, b, n)) in centers.iter_mut().zip(&sums) {
+            if n > 0 {
+                *center = (l / n as f32, a / n as f32, b / n as f32);
+            }
+        }
+    }
+
+    let mut sums = vec![(0.0, 0.0, 0.0, 0usize); k];
+    for (&(_, p), &cluster) in samples.iter().zip(&assignment) {
+        let sum = &mut sums[cluster];
+        *sum = (sum.0 + p.0, sum.1 + p.1, sum.2 + p.2, sum.3 + 1);
+    }
+    let mut clusters: Vec<_> = sums.into_iter().filter(|&(.., n)| n > 0).collect();
+    clusters.sort_by_key(|&(.., n)| std::cmp::Reverse(n));
+    clusters.into_iter().map(|(l, a, b, n)| oklab_to_u8((l / n as f32, a / n as f32, b / n as f32))).collect()
+}
+
+/// `n` colors that represent an image, most common first, by median cut
+/// over every pixel in the space `metric` measures distance in. The box
+/// with the widest spread along any axis is split at its median until
+/// there are `n`, or until every box is a single color; each color is its
+/// box's mean in Oklab. Shrink the image first for speed on very large
+/// inputs.
+pub fn extract_palette(img: &DynamicImage, n: usize, metric: ColorDistance) -> Vec<(u8, u8, u8)> {
+    let pixels = opaque_pixels(img, metric);
+    if pixels.is_empty() || n == 0 {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < n {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (i, widest_axis(b)))
+            .max_by(|x, y| x.1.1.total_cmp(&y.1.1));
+        let Some((index, (axis, spread))) = widest else { break };
+        if spread <= 0.0 {
+            break;
+        }
+
+        let mut splitting = boxes.swap_remove(index);
+        splitting.sort_by(|x, y| channel(x.0, axis).total_cmp(&channel(y.0, axis)));
+        let upper = splitting.split_off(splitting.len() / 2);
+        boxes.push(splitting);
+        boxes.push(upper);
+    }
+
+    boxes.sort_by_key(|b| std::cmp::Reverse(b.len()));
+    boxes
+        .iter()
+        .map(|b| {
+            let n = b.len() as f32;
+            oklab_to_u8(b.iter().fold((0.0, 0.0, 0.0), |m, &(_, p)| (m.0 + p.0 / n, m.1 + p.1 / n, m.2 + p.2 / n)))
+        })
+        .collect()
+}
+
+/// The axis a box spreads furthest along (0 to 2, as L, a and b in Oklab),
+/// and that spread.
+fn widest_axis(pixels: &[Sample]) -> (usize, f32) {
+    (0..3)
+        .map(|axis| {
+            let (lo, hi) = pixels.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &(p, _)| {
+                let v = channel(p, axis);
+                (lo.min(v), hi.max(v))
+            });
+            (axis, hi - lo)
+        })
+        .max_by(|x, y| x.1.total_cmp(&y.1))
+        .unwrap()
+}
+
+fn channel(p: (f32, f32, f32), axis: usize) -> f32 {
+    match axis {
+        0 => p.0,
+        1 => p.1,
+        _ => p.2,
+    }
+}
+
+/// A pixel where `metric` places it, and in Oklab.
+type Sample = ((f32, f32, f32), (f32, f32, f32));
+
+/// The opaque pixels of an image.
+fn opaque_pixels(img: &DynamicImage, metric: ColorDistance) -> Vec<Sample> {
+    img.to_rgba8()
+        .pixels()
+        .filter(|p| p[3] >= MIN_ALPHA)
+        .map(|p| {
+            let [r, g, b] = [p[0], p[1], p[2]].map(|c| srgb_to_linear(c as f32 / 255.0));
+            (metric.coordinates((r, g, b)), linear_to_oklab(r, g, b))
+        })
+        .collect()
+}
+
+/// Index of the color in `colors` closest to `target`.
+fn nearest(colors: &[(f32, f32, f32)], target: (f32, f32, f32)) -> usize {
+    colors
+        .iter()
+        .map(|&c| squared_distance(c, target))
+        .enumerate()
+        .min_by(|x, y| x.1.total_cmp(&y.1))
+        .map_or(0, |(i, _)| i)
+}
+
+fn oklab_to_u8((l, a, b): (f32, f32, f32)) -> (u8, u8, u8) {
+    let (r, g, b) = oklab_to_srgb(l, a, b);
+    let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    (to_u8(r), to_u8(g), to_u8(b))
+}
@@ -0,0 +1,45 @@
+use crate::error::Result;
+use crate::frames::frames;
+use crate::icon::IconEntry;
+use crate::tonemap::Tonemap;
+use image::{ExtendedColorType, ImageDecoder, ImageFormat, ImageReader};
+use std::path::Path;
+
+/// What an image file holds, read from its header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageInfo {
+    /// The format sniffed from the contents (or the extension)
+    pub format: Option<ImageFormat>,
+    pub width: u32,
+    pub height: u32,
+    /// The color type stored in the file, before any conversion
+    pub color_type: ExtendedColorType,
+    /// Number of frames: 1 for stills
+    pub frames: usize,
+}
+
+impl ImageInfo {
+    /// Bits per channel, or `None` when the color type is opaque to `image`.
+    pub fn bit_depth(&self) -> Option<u16> {
+        let channels = self.color_type.channel_count() as u16;
+        (channels > 0).then(|| self.color_type.bits_per_pixel() / channels)
+    }
+}
+
+/// Reads an image's metadata. Only the header is decoded, except that
+/// animated GIF and WebP inputs are streamed through to count their frames.
+pub fn probe(path: &Path) -> Result<ImageInfo> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+    let format = reader.format();
+    let decoder = reader.into_decoder()?;
+    let (width, height) = decoder.dimensions();
+    let color_type = decoder.original_color_type();
+    drop(decoder);
+
+    let frames = match format {
+        Some(ImageFormat::Gif | ImageFormat::WebP) => frames(path, Tonemap::default(), IconEntry::default(), None)?.try_fold(0, |n, frame| frame.map(|_| n + 1))?,
+        _ => 1,
+    };
+
+    Ok(ImageInfo { format, width, height, color_type, frames })
+}
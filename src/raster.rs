@@ -0,0 +1,216 @@
+use crate::cell::Grid;
+use crate::quadrant::QUADRANTS;
+use crate::sextant::SEXTANTS;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Frame, Rgba, RgbaImage};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Colors standing in for the terminal defaults when a cell leaves them unset.
+const DEFAULT_FG: Rgba<u8> = Rgba([204, 204, 204, 255]);
+const DEFAULT_BG: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// 5x7 bitmaps for the ASCII ramp, one row per byte with bit 4 leftmost.
+const ASCII_FONT: [(char, [u8; 7]); 9] = [
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    ('*', [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000]),
+    ('#', [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('@', [0b01110, 0b10001, 0b10111, 0b10101, 0b10111, 0b10000, 0b01110]),
+];
+
+/// Glyph coverage masks at a fixed cell size, rasterized on first use.
+///
+/// Most glyphs the renderers emit are built from a regular grid of sub-cells
+/// (Braille dots, quadrant and sextant blocks), so those are generated from
+/// the same bit tables the renderers use; eighth blocks are cut from the
+/// cell and only the ASCII ramp is a bitmap.
+pub struct GlyphAtlas {
+    cell_w: u32,
+    cell_h: u32,
+    glyphs: HashMap<char, Vec<bool>>,
+}
+
+impl GlyphAtlas {
+    pub fn new(cell_w: u32, cell_h: u32) -> Self {
+        GlyphAtlas { cell_w, cell_h, glyphs: HashMap::new() }
+    }
+
+    /// Row-major coverage mask of `c`, `cell_w * cell_h` entries long.
+    pub fn glyph(&mut self, c: char) -> &[bool] {
+        let (w, h) = (self.cell_w, self.cell_h);
+        self.glyphs.entry(c).or_insert_with(|| rasterize_glyph(c, w, h))
+    }
+}
+
+fn rasterize_glyph(c: char, w: u32, h: u32) -> Vec<bool> {
+    let mut mask = vec![false; (w * h) as usize];
+
+    if (0x2800..=0x28FF).contains(&(c as u32)) {
+        // Braille: round dots centered in a 2x4 grid of sub-cells
+        let bits = c as u32 - 0x2800;
+        let (sub_w, sub_h) = (w as f32 / 2.0, h as f32 / 4.0);
+        let radius = sub_w.min(sub_h) * 0.35;
+        for (dx, dy, bit) in crate::braille::BRAILLE_COORDS {
+            if bits & bit == 0 {
+                continue;
+            }
+            let cx = (dx as f32 + 0.5) * sub_w;
+            let cy = (dy as f32 + 0.5) * sub_h;
+            for py in 0..h {
+                for px in 0..w {
+                    let ox = px as f32 + 0.5 - cx;
+                    let oy = py as f32 + 0.5 - cy;
+                    if ox * ox + oy * oy <= radius * radius {
+                        mask[(py * w + px) as usize] = true;
+                    }
+                }
+            }
+        }
+        return mask;
+    }
+
+    if let Some((_, rows)) = ASCII_FONT.iter().find(|(a, _)| *a == c) {
+        // Scale the 5x7 bitmap into the cell with a one pixel margin (6x8)
+        for py in 0..h {
+            for px in 0..w {
+                let fx = px * 6 / w;
+                let fy = py * 8 / h;
+                if fx < 5 && fy < 7 && rows[fy as usize] & (0b10000 >> fx) != 0 {
+                    mask[(py * w + px) as usize] = true;
+                }
+            }
+        }
+        return mask;
+    }
+
+    if c == '▀' {
+        mask[..(w * (h / 2)) as usize].fill(true);
+        return mask;
+    }
+
+    if ('▁'..='▇').contains(&c) {
+        // Lower eighths: the bottom n/8 of the cell
+        let eighths = c as u32 - 0x2580;
+        let top = h - (h * eighths + 4) / 8;
+        mask[(w * top) as usize..].fill(true);
+        return mask;
+    }
+
+    if ('▉'..='▏').contains(&c) {
+        // Left eighths, counting down from seven: the left n/8 of the cell
+        let right = (w * (0x2590 - c as u32) + 4) / 8;
+        for row in mask.chunks_exact_mut(w as usize) {
+            row[..right as usize].fill(true);
+        }
+        return mask;
+    }
+
+    if let Some(quarters) = ['░', '▒', '▓'].iter().position(|&s| s == c) {
+        // Shades: an ordered pattern lighting one, two or three pixels in four
+        let share = (quarters + 1) as f32 / 4.0;
+        for py in 0..h {
+            for px in 0..w {
+                mask[(py * w + px) as usize] = crate::dither::bayer(px, py) < share;
+            }
+        }
+        return mask;
+    }
+
+    // Block glyphs: bit (row * cols + col) lights that sub-cell
+    let blocks = QUADRANTS.iter().position(|&q| q == c).map(|bits| (2, 2, bits))
+        .or_else(|| SEXTANTS.iter().position(|&s| s == c).map(|bits| (2, 3, bits)));
+
+    if let Some((cols, rows, bits)) = blocks {
+        for py in 0..h {
+            for px in 0..w {
+                let col = px * cols / w;
+                let row = py * rows / h;
+                if bits & (1 << (row * cols + col)) != 0 {
+                    mask[(py * w + px) as usize] = true;
+                }
+            }
+        }
+    }
+    mask
+}
+
+/// Draws a grid as an image, one `cell_w x cell_h` block per character cell.
+pub fn rasterize(grid: &Grid, atlas: &mut GlyphAtlas) -> RgbaImage {
+    let (cell_w, cell_h) = (atlas.cell_w, atlas.cell_h);
+    let cols = grid.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+    let mut img = RgbaImage::new(cols * cell_w, grid.len() as u32 * cell_h);
+
+    for (y, row) in grid.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let mask = atlas.glyph(cell.glyph);
+            let fg = cell.fg.map_or(DEFAULT_FG, |(r, g, b)| Rgba([r, g, b, 255]));
+            let bg = cell.bg.map_or(DEFAULT_BG, |(r, g, b)| Rgba([r, g, b, 255]));
+            for py in 0..cell_h {
+                for px in 0..cell_w {
+                    let lit = mask[(py * cell_w + px) as usize];
+                    img.put_pixel(
+                        x as u32 * cell_w + px,
+                        y as u32 * cell_h + py,
+                        if lit { fg } else { bg },
+                    );
+                }
+            }
+        }
+    }
+    img
+}
+
+/// Encodes a looping GIF a frame at a time, so a capture holds no more
+/// than the frame being written. The file is created with the first frame,
+/// whose size the GIF takes; later frames are padded with black or cropped
+/// to it.
+pub struct GifWriter {
+    path: PathBuf,
+    encoder: Option<GifEncoder<BufWriter<File>>>,
+    size: (u32, u32),
+    frames: usize,
+}
+
+impl GifWriter {
+    pub fn new(path: &Path) -> Self {
+        GifWriter { path: path.to_path_buf(), encoder: None, size: (0, 0), frames: 0 }
+    }
+
+    /// Encodes the next frame.
+    pub fn push(&mut self, frame: Frame) -> image::ImageResult<()> {
+        let encoder = match &mut self.encoder {
+            Some(encoder) => encoder,
+            None => {
+                let file = BufWriter::new(File::create(&self.path)?);
+                // Speed 10 keeps NeuQuant fast on large captures at a small quality cost
+                let mut encoder = GifEncoder::new_with_speed(file, 10);
+                encoder.set_repeat(Repeat::Infinite)?;
+                self.size = frame.buffer().dimensions();
+                self.encoder.insert(encoder)
+            }
+        };
+        let frame = if frame.buffer().dimensions() == self.size {
+            frame
+        } else {
+            let delay = frame.delay();
+            let mut canvas = RgbaImage::from_pixel(self.size.0, self.size.1, Rgba([0, 0, 0, 255]));
+            image::imageops::overlay(&mut canvas, frame.buffer(), 0, 0);
+            Frame::from_parts(canvas, 0, 0, delay)
+        };
+        encoder.encode_frame(frame)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// How many frames have been written.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
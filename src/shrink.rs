@@ -0,0 +1,194 @@
+use crate::btc::linear_to_u8;
+use crate::color::srgb_to_linear;
+use crate::error::Result;
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use tiff::decoder::{Decoder as TiffDecoder, DecodingResult};
+use tiff::tags::Tag;
+
+/// How big still images are decoded before being fit to the output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DecodeScale {
+    /// At their full size
+    #[default]
+    Full,
+    /// Shrunk while decoding where the format allows (PNG and TIFF, a row
+    /// or strip at a time), so memory follows the output's size rather
+    /// than the input's; other formats decode in full
+    Auto,
+}
+
+/// Decodes a PNG or TIFF still from `input`, shrunk by a whole factor to
+/// no less than twice the `fit` box each way, so the final resize still has
+/// pixels to filter. Only the shrunk image and one row or strip of the
+/// input are held at a time. `None`, with `input` rewound, for anything
+/// that can't be streamed (other formats, interlaced PNGs, planar or
+/// unusual TIFFs), for images too small to shrink, and for files that fail
+/// to decode, so a full decode can report the error as usual.
+pub(crate) fn decode<R: BufRead + Seek>(input: &mut R, format: ImageFormat, fit: (u32, u32)) -> Result<Option<RgbaImage>> {
+    let start = input.stream_position()?;
+    let shrunk = match format {
+        ImageFormat::Png => decode_png(&mut *input, fit),
+        ImageFormat::Tiff => decode_tiff(&mut *input, fit),
+        _ => None,
+    };
+    if shrunk.is_none() {
+        input.seek(SeekFrom::Start(start))?;
+    }
+    Ok(shrunk)
+}
+
+/// How many pixels each way a `width` x `height` image can be averaged
+/// down by while staying at least twice the `fit` box.
+fn factor((width, height): (u32, u32), (fit_width, fit_height): (u32, u32)) -> u32 {
+    (width / (2 * fit_width.max(1))).min(height / (2 * fit_height.max(1))).max(1)
+}
+
+fn decode_png(input: impl BufRead + Seek, fit: (u32, u32)) -> Option<RgbaImage> {
+    let mut decoder = png::Decoder::new(input);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let (width, height) = (reader.info().width, reader.info().height);
+    let factor = factor((width, height), fit);
+    // Interlaced rows come a pass at a time rather than top to bottom
+    if factor == 1 || reader.info().interlaced {
+        return None;
+    }
+    let channels = match reader.output_color_type().0 {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return None,
+    };
+
+    let mut shrink = Shrink::new((width, height), factor);
+    while let Some(row) = reader.next_row().ok()? {
+        shrink.push(row.data().chunks_exact(channels));
+    }
+    Some(shrink.finish())
+}
+
+fn decode_tiff(input: impl Read + Seek, fit: (u32, u32)) -> Option<RgbaImage> {
+    let mut decoder = TiffDecoder::new(input).ok()?;
+    let (width, height) = decoder.dimensions().ok()?;
+    let factor = factor((width, height), fit);
+    let channels = match decoder.colortype().ok()? {
+        tiff::ColorType::Gray(8 | 16) => 1,
+        tiff::ColorType::GrayA(8 | 16) => 2,
+        tiff::ColorType::RGB(8 | 16) => 3,
+        tiff::ColorType::RGBA(8 | 16) => 4,
+        _ => return None,
+    };
+    // Planar files keep each channel in chunks of its own
+    let planar = decoder.find_tag_unsigned::<u16>(Tag::PlanarConfiguration).ok()?.is_some_and(|p| p != 1);
+    if factor == 1 || planar {
+        return None;
+    }
+
+    // Strips span the width; tiles are read a row of them at a time
+    let (chunk_width, chunk_height) = decoder.chunk_dimensions();
+    let across = width.div_ceil(chunk_width);
+    let stride = width as usize * channels;
+    let mut band = vec![0u8; stride * chunk_height as usize];
+    let mut shrink = Shrink::new((width, height), factor);
+    for down in 0..height.div_ceil(chunk_height) {
+        let mut rows = 0;
+        for column in 0..across {
+            let index = down * across + column;
+            let (data_width, data_height) = decoder.chunk_data_dimensions(index);
+            let samples: Vec<u8> = match decoder.read_chunk(index).ok()? {
+                DecodingResult::U8(samples) => samples,
+                DecodingResult::U16(samples) => samples.into_iter().map(|s| ((s as u32 * 255 + 32767) / 65535) as u8).collect(),
+                _ => return None,
+            };
+            let chunk_stride = data_width as usize * channels;
+            let left = (column * chunk_width) as usize * channels;
+            for (y, row) in samples.chunks_exact(chunk_stride).take(data_height as usize).enumerate() {
+                band[y * stride + left..][..chunk_stride].copy_from_slice(row);
+            }
+            rows = data_height as usize;
+        }
+        for row in band.chunks_exact(stride).take(rows) {
+            shrink.push(row.chunks_exact(channels));
+        }
+    }
+    Some(shrink.finish())
+}
+
+/// Averages `factor` x `factor` blocks of an image fed to it a row at a
+/// time, in linear light and weighted by alpha, so transparent pixels
+/// don't darken the edges next to them. Blocks at the right and bottom
+/// edges can be cut short.
+struct Shrink {
+    factor: u32,
+    /// Linear light of each sRGB byte
+    linear: [f32; 256],
+    /// Per output pixel of the current row: premultiplied red, green and
+    /// blue, alpha, and the number of pixels summed
+    sums: Vec<[f32; 5]>,
+    rows: u32,
+    out: RgbaImage,
+}
+
+impl Shrink {
+    fn new((width, height): (u32, u32), factor: u32) -> Self {
+        let (width, height) = (width.div_ceil(factor), height.div_ceil(factor));
+        Shrink {
+            factor,
+            linear: std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)),
+            sums: vec![[0.0; 5]; width as usize],
+            rows: 0,
+            out: RgbaImage::new(width, height),
+        }
+    }
+
+    /// Adds the next row, as gray, gray and alpha, RGB or RGBA pixels.
+    fn push<'a>(&mut self, pixels: impl Iterator<Item = &'a [u8]>) {
+        for (x, pixel) in pixels.enumerate() {
+            let [r, g, b, a] = match *pixel {
+                [l] => [l, l, l, 255],
+                [l, a] => [l, l, l, a],
+                [r, g, b] => [r, g, b, 255],
+                [r, g, b, a] => [r, g, b, a],
+                _ => unreachable!("pixels have one to four channels"),
+            };
+            let alpha = a as f32 / 255.0;
+            let sum = &mut self.sums[x / self.factor as usize];
+            sum[0] += self.linear[r as usize] * alpha;
+            sum[1] += self.linear[g as usize] * alpha;
+            sum[2] += self.linear[b as usize] * alpha;
+            sum[3] += alpha;
+            sum[4] += 1.0;
+        }
+        self.rows += 1;
+        if self.rows.is_multiple_of(self.factor) {
+            self.flush();
+        }
+    }
+
+    /// Writes out the row of blocks summed so far.
+    fn flush(&mut self) {
+        let y = (self.rows - 1) / self.factor;
+        if y >= self.out.height() {
+            return;
+        }
+        for (x, sum) in self.sums.iter_mut().enumerate() {
+            let [r, g, b, alpha, count] = std::mem::take(sum);
+            let pixel = if alpha > 0.0 {
+                let (r, g, b) = linear_to_u8((r / alpha, g / alpha, b / alpha));
+                Rgba([r, g, b, (alpha / count * 255.0).round() as u8])
+            } else {
+                Rgba([0; 4])
+            };
+            self.out.put_pixel(x as u32, y, pixel);
+        }
+    }
+
+    fn finish(mut self) -> RgbaImage {
+        if !self.rows.is_multiple_of(self.factor) {
+            self.flush();
+        }
+        self.out
+    }
+}
@@ -0,0 +1,97 @@
+//! The sRGB transfer functions eight channels at a time, on `wide`'s
+//! `f32x8`, which compiles to AVX or SSE (or NEON) where the target has
+//! them. `powf` is rebuilt from `log2` and `exp2` polynomials, which land
+//! within a few f32 epsilons of it.
+
+use wide::{f32x8, u32x8};
+
+/// Channels converted together.
+pub(crate) const LANES: usize = 8;
+
+/// `2 / (k ln 2)` for odd `k` from 1 to 9: the series for `log2(m)` in
+/// `s = (m - 1) / (m + 1)`.
+const LOG2_SERIES: [f32; 5] = [2.885_39, 0.961_797, 0.577_078_2, 0.412_198_7, 0.320_599];
+
+/// `1 / k!` for `k` from 0 to 9: the series for `exp(t)`.
+const EXP_SERIES: [f32; 10] = [
+    1.0,
+    1.0,
+    0.5,
+    1.0 / 6.0,
+    1.0 / 24.0,
+    1.0 / 120.0,
+    1.0 / 720.0,
+    1.0 / 5040.0,
+    1.0 / 40320.0,
+    1.0 / 362_880.0,
+];
+
+/// Runs `convert` over each whole group of lanes in `values`, returning
+/// what's left over for the scalar path.
+pub(crate) fn convert(values: &mut [f32], convert: fn(f32x8) -> f32x8) -> &mut [f32] {
+    let mut chunks = values.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        let lanes: [f32; LANES] = (*chunk).try_into().expect("chunks are LANES long");
+        chunk.copy_from_slice(&convert(f32x8::new(lanes)).to_array());
+    }
+    chunks.into_remainder()
+}
+
+/// 1.5 * 2^23, the float whose last place is 1.
+const ROUNDER: f32 = 12_582_912.0;
+
+/// 2^23, the float whose low bits hold a small whole number as is.
+const TWO_23: f32 = 8_388_608.0;
+
+/// [`crate::color::srgb_to_linear`] on every lane.
+pub(crate) fn srgb_to_linear(c: f32x8) -> f32x8 {
+    let x = unit(c);
+    let curve = pow((x + f32x8::splat(0.055)) / f32x8::splat(1.055), 2.4);
+    x.simd_le(f32x8::splat(0.04045)).select(x / f32x8::splat(12.92), curve)
+}
+
+/// [`crate::color::linear_to_srgb`] on every lane.
+pub(crate) fn linear_to_srgb(c: f32x8) -> f32x8 {
+    let x = unit(c);
+    // The curve is only kept above the linear toe, so zero can't reach the log
+    let curve = f32x8::splat(1.055) * pow(x.max(f32x8::splat(f32::MIN_POSITIVE)), 1.0 / 2.4) - f32x8::splat(0.055);
+    x.simd_le(f32x8::splat(0.0031308)).select(f32x8::splat(12.92) * x, curve)
+}
+
+/// Clamps to 0-1 with NaN as 0, like the scalar path.
+fn unit(c: f32x8) -> f32x8 {
+    c.simd_ge(f32x8::ZERO).select(c.min(f32x8::ONE), f32x8::ZERO)
+}
+
+/// `x` to the power `p`, for positive normal `x`.
+fn pow(x: f32x8, p: f32) -> f32x8 {
+    exp2(f32x8::splat(p) * log2(x))
+}
+
+fn log2(x: f32x8) -> f32x8 {
+    let bits = x.to_bits();
+    // The exponent byte, read back as a float by setting it in the low bits of 2^23
+    let biased = f32x8::from_bits((bits >> 23) & u32x8::splat(0xff) | u32x8::splat(TWO_23.to_bits())) - f32x8::splat(TWO_23);
+    let m = f32x8::from_bits(bits & u32x8::splat(0x007f_ffff) | u32x8::splat(0x3f80_0000));
+    // Centering the mantissa on 1 keeps `s` small, so the series converges fast
+    let high = m.simd_gt(f32x8::splat(std::f32::consts::SQRT_2));
+    let m = high.select(m * f32x8::splat(0.5), m);
+    let exponent = biased - f32x8::splat(127.0) + high.select(f32x8::ONE, f32x8::ZERO);
+
+    let s = (m - f32x8::ONE) / (m + f32x8::ONE);
+    let s2 = s * s;
+    let series = LOG2_SERIES.iter().rev().fold(f32x8::ZERO, |acc, &k| acc.mul_add(s2, f32x8::splat(k)));
+    exponent + s * series
+}
+
+fn exp2(y: f32x8) -> f32x8 {
+    let y = y.clamp(f32x8::splat(-126.0), f32x8::splat(126.0));
+    // Adding 1.5 * 2^23 rounds to a whole number and leaves it in the low
+    // bits, without a float to int conversion
+    let shifted = y + f32x8::splat(ROUNDER);
+    let whole = shifted - f32x8::splat(ROUNDER);
+    let exponent = (shifted.to_bits() - u32x8::splat(ROUNDER.to_bits()) + u32x8::splat(127)) << 23;
+    let t = (y - whole) * f32x8::splat(std::f32::consts::LN_2);
+    let fraction = EXP_SERIES.iter().rev().fold(f32x8::ZERO, |acc, &k| acc.mul_add(t, f32x8::splat(k)));
+    fraction * f32x8::from_bits(exponent)
+}
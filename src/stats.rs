@@ -0,0 +1,46 @@
+use crate::cell::Grid;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Diagnostics gathered from rendered grids, for tuning dithering.
+#[derive(Debug, Default)]
+pub struct Stats {
+    cells: usize,
+    lit: usize,
+    colors: HashSet<(u8, u8, u8)>,
+    braille_cells: usize,
+    braille_dots: u32,
+}
+
+impl Stats {
+    pub fn add_grid(&mut self, grid: &Grid) {
+        for cell in grid.iter().flatten() {
+            self.cells += 1;
+            // Blank Braille (U+2800) inks nothing, just like a space
+            if cell.glyph != ' ' && cell.glyph != '\u{2800}' {
+                self.lit += 1;
+            }
+            self.colors.extend(cell.fg);
+            self.colors.extend(cell.bg);
+
+            if ('\u{2800}'..='\u{28FF}').contains(&cell.glyph) {
+                self.braille_cells += 1;
+                self.braille_dots += (cell.glyph as u32 - 0x2800).count_ones();
+            }
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lit_fraction = self.lit as f32 / self.cells.max(1) as f32;
+        writeln!(f, "cells: {}", self.cells)?;
+        writeln!(f, "lit: {} ({:.1}%)", self.lit, lit_fraction * 100.0)?;
+        write!(f, "unique colors: {}", self.colors.len())?;
+        if self.braille_cells > 0 {
+            let dots = self.braille_dots as f32 / self.braille_cells as f32;
+            write!(f, "\ndots per cell: {:.2} / 8", dots)?;
+        }
+        Ok(())
+    }
+}
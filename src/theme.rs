@@ -0,0 +1,102 @@
+use crate::color::LumaWeights;
+use crossterm::terminal;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::sync::OnceLock;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait for the terminal to answer the background query.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The terminal's background color, asked for once with the OSC 11 query and
+/// remembered. `None` when stdout isn't a terminal or it doesn't answer in
+/// time, which is common: many terminals and multiplexers ignore the query.
+pub fn background() -> Option<(u8, u8, u8)> {
+    static BACKGROUND: OnceLock<Option<(u8, u8, u8)>> = OnceLock::new();
+    *BACKGROUND.get_or_init(|| query_background().ok().flatten())
+}
+
+/// Whether the terminal's background is known to be light.
+pub fn is_light_background() -> bool {
+    background().is_some_and(|(r, g, b)| LumaWeights::default().luma(r as f32, g as f32, b as f32) > 127.5)
+}
+
+fn query_background() -> io::Result<Option<(u8, u8, u8)>> {
+    // The reply ends with BEL or ST (ESC \)
+    let reply = query(b"\x1b]11;?\x07", |reply| reply.ends_with(b"\x07") || reply.ends_with(b"\x1b\\"))?;
+    Ok(reply.as_deref().and_then(parse_osc11_reply))
+}
+
+/// How many columns the terminal advances the cursor for `glyph`: 2 where a
+/// font or width table draws it double-width, as some do for braille and
+/// sextants. Measured by printing it at the start of the line and asking
+/// for the cursor position, then erased again. `None` when stdout isn't a
+/// terminal or it doesn't answer in time.
+pub fn glyph_width(glyph: char) -> Option<u32> {
+    let request = format!("\r{}\x1b[6n", glyph);
+    // The reply is `ESC [ row ; column R`
+    let reply = query(request.as_bytes(), |reply| reply.ends_with(b"R")).ok().flatten();
+    // Erase the test glyph whether or not the terminal answered
+    let _ = io::stdout().write_all(b"\r\x1b[K").and_then(|()| io::stdout().flush());
+    parse_cursor_column(&reply?).map(|column| column.saturating_sub(1))
+}
+
+/// Parses the column out of a cursor position report like `ESC [ 12 ; 3 R`.
+pub fn parse_cursor_column(reply: &[u8]) -> Option<u32> {
+    let reply = std::str::from_utf8(reply).ok()?;
+    let (_, position) = reply.rsplit_once("\x1b[")?;
+    position.strip_suffix('R')?.split_once(';')?.1.parse().ok()
+}
+
+/// Writes `request` to the terminal and reads its reply up to the byte
+/// `is_done` accepts, waiting at most [`QUERY_TIMEOUT`]. `None` when stdout
+/// isn't a terminal or no full reply came in time.
+fn query(request: &[u8], is_done: impl Fn(&[u8]) -> bool + Send + 'static) -> io::Result<Option<Vec<u8>>> {
+    if !io::stdout().is_terminal() {
+        return Ok(None);
+    }
+    let mut tty = File::options().read(true).write(true).open("/dev/tty")?;
+
+    // Raw mode keeps the reply from being echoed or held back until a newline
+    terminal::enable_raw_mode()?;
+    let reply = tty.write_all(request).and_then(|()| {
+        let mut reader = tty.try_clone()?;
+        let (tx, rx) = mpsc::channel();
+        // A terminal that never answers leaves this thread blocked until exit
+        thread::spawn(move || {
+            let mut reply = Vec::new();
+            let mut byte = [0];
+            while reader.read(&mut byte).is_ok_and(|n| n == 1) {
+                reply.push(byte[0]);
+                if is_done(&reply) {
+                    break;
+                }
+            }
+            let _ = tx.send(reply);
+        });
+        Ok(rx.recv_timeout(QUERY_TIMEOUT).ok())
+    });
+    terminal::disable_raw_mode()?;
+    reply
+}
+
+/// Parses a reply like `ESC ] 11 ; rgb:ffff/ffff/dddd BEL`, where each
+/// channel has 1 to 4 hex digits.
+pub fn parse_osc11_reply(reply: &[u8]) -> Option<(u8, u8, u8)> {
+    let reply = std::str::from_utf8(reply).ok()?;
+    let spec = reply.split_once("rgb:")?.1;
+    let spec = spec.trim_end_matches(['\x07', '\\']).trim_end_matches('\x1b');
+
+    let mut channels = spec.split('/').map(|hex| {
+        if !(1..=4).contains(&hex.len()) {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1u32 << (4 * hex.len())) - 1;
+        Some((value as f32 / max as f32 * 255.0).round() as u8)
+    });
+    let rgb = (channels.next()??, channels.next()??, channels.next()??);
+    channels.next().is_none().then_some(rgb)
+}
@@ -0,0 +1,49 @@
+use crate::color::linear_to_srgb_slice;
+use image::{DynamicImage, RgbaImage};
+
+/// How floating-point inputs (OpenEXR, Radiance HDR), whose linear values
+/// can run well past 1.0, are brought into displayable range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Tonemap {
+    /// x / (1 + x) per channel: highlights roll off smoothly and never clip
+    #[default]
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve: more contrast than
+    /// Reinhard, with a slight toe in the shadows
+    Aces,
+    /// Clamp to 1.0, blowing out everything brighter
+    Clip,
+}
+
+impl Tonemap {
+    /// Maps a linear value of any brightness into 0-1.
+    pub fn apply(self, x: f32) -> f32 {
+        let x = x.max(0.0);
+        let mapped = match self {
+            Tonemap::Reinhard => x / (1.0 + x),
+            Tonemap::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+            Tonemap::Clip => x,
+        };
+        mapped.clamp(0.0, 1.0)
+    }
+}
+
+/// Converts a decoded image to 8-bit sRGB. Floating-point images hold
+/// linear light, so they are tone-mapped with `tonemap` and then
+/// sRGB-encoded; every other image converts as is.
+pub fn to_rgba8(img: DynamicImage, tonemap: Tonemap) -> RgbaImage {
+    if !matches!(img, DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)) {
+        return img.into_rgba8();
+    }
+
+    let linear = img.into_rgba32f();
+    let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut rgb: Vec<f32> = linear.pixels().flat_map(|p| p.0[..3].iter().map(|&c| tonemap.apply(c))).collect();
+    linear_to_srgb_slice(&mut rgb);
+    let pixels = linear
+        .pixels()
+        .zip(rgb.chunks_exact(3))
+        .flat_map(|(p, rgb)| [to_u8(rgb[0]), to_u8(rgb[1]), to_u8(rgb[2]), to_u8(p[3])])
+        .collect();
+    RgbaImage::from_raw(linear.width(), linear.height(), pixels).expect("four channels per pixel")
+}
@@ -0,0 +1,83 @@
+//! The legacy-blocks mode draws each cell as the eighth-block bar (or
+//! shade) that best follows it; the eighths mode fills each cell as high as
+//! it is bright.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use jiv2::dither::Dither;
+use jiv2::{Dimension, Mode, RenderOptions};
+
+/// The one cell a 64x128 image renders to, drawn by `lit(x, y)`: bright
+/// green where it's true, dark blue elsewhere.
+fn cell(lit: impl Fn(u32, u32) -> bool) -> jiv2::cell::Cell {
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 128, |x, y| {
+        if lit(x, y) { Rgba([40, 220, 90, 255]) } else { Rgba([10, 20, 80, 255]) }
+    }));
+    let opts = RenderOptions {
+        mode: Mode::LegacyBlocks,
+        width: Some(Dimension::Cells(1)),
+        height: Some(Dimension::Cells(1)),
+        ..Default::default()
+    };
+    let grid = Mode::LegacyBlocks.render(&img, &opts);
+    assert_eq!((grid.len(), grid[0].len()), (1, 1));
+    grid[0][0]
+}
+
+/// The resize softens the edges a little, so colors are only close.
+fn green(color: Option<(u8, u8, u8)>) -> u8 {
+    color.unwrap().1
+}
+
+#[test]
+fn bars_follow_the_fill() {
+    // Lit from five eighths of the way down
+    let bar = cell(|_, y| y >= 80);
+    assert_eq!(bar.glyph, '▃');
+    assert!(green(bar.fg) > 200 && green(bar.bg) < 50);
+    // Lit across the left quarter
+    assert_eq!(cell(|x, _| x < 16).glyph, '▎');
+    // A bar on top is the lower block the other way round
+    let top = cell(|_, y| y < 32);
+    assert_eq!(top.glyph, '▆');
+    assert!(green(top.fg) < 50 && green(top.bg) > 200);
+}
+
+#[test]
+fn flat_cells_are_full_blocks() {
+    let flat = cell(|_, _| true);
+    assert_eq!((flat.glyph, flat.fg), ('█', Some((40, 220, 90))));
+}
+
+#[test]
+fn scattered_dots_are_shaded() {
+    let dots = cell(|x, y| x % 16 < 4 && y % 16 < 4 && (x / 16 + y / 16) % 2 == 0);
+    assert!(['░', '▒', '▓'].contains(&dots.glyph), "got {}", dots.glyph);
+}
+
+#[test]
+fn eighths_fill_as_high_as_the_luma() {
+    let render = |gray: u8, dither: Dither| {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba([gray, gray, gray, 255])));
+        let opts = RenderOptions {
+            mode: Mode::Eighths,
+            width: Some(Dimension::Cells(16)),
+            height: Some(Dimension::Cells(8)),
+            dither,
+            ..Default::default()
+        };
+        let grid = Mode::Eighths.render(&img, &opts);
+        // The bars are in the terminal's own colors
+        assert!(grid.iter().flatten().all(|cell| cell.fg.is_none() && cell.bg.is_none()));
+        grid.iter().flatten().map(|cell| cell.glyph).collect::<Vec<_>>()
+    };
+    assert!(render(0, Dither::None).iter().all(|&glyph| glyph == ' '));
+    assert!(render(255, Dither::None).iter().all(|&glyph| glyph == '█'));
+    assert!(render(128, Dither::None).iter().all(|&glyph| glyph == '▄'));
+
+    // Four and a half eighths dithers between the two nearest heights
+    let glyphs = render(143, Dither::FloydSteinberg);
+    let height = |glyph| jiv2::eighths::LEVELS.iter().position(|&level| level == glyph).unwrap();
+    assert!(glyphs.iter().all(|&glyph| glyph == '▄' || glyph == '▅'));
+    let mean = glyphs.iter().map(|&glyph| height(glyph) as f32).sum::<f32>() / glyphs.len() as f32;
+    assert!((mean - 4.5).abs() < 0.1, "mean height {}", mean);
+}
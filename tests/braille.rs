@@ -0,0 +1,170 @@
+//! Braille-only options, and the dot layout every braille glyph is built
+//! from.
+
+use image::{DynamicImage, Rgb, RgbImage};
+use jiv2::braille::BRAILLE_COORDS;
+use jiv2::{Dimension, Mode, RenderOptions};
+use std::collections::BTreeSet;
+
+#[test]
+fn tint_colors_every_dot() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| Rgb([(x * 4) as u8, (y * 4) as u8, 200])));
+    let tint = (255, 136, 0);
+    let opts = RenderOptions {
+        mode: Mode::Braille,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(8)),
+        tint: Some(tint),
+        ..Default::default()
+    };
+
+    let grid = Mode::Braille.render(&img, &opts);
+    let dotted: Vec<_> = grid.iter().flatten().filter(|cell| !cell.is_blank()).collect();
+    assert!(!dotted.is_empty());
+    assert!(dotted.iter().all(|cell| cell.fg == Some(tint)));
+}
+
+#[test]
+fn antialias_edges_dims_sparse_edge_cells_only() {
+    // A white disc on black, so its rim cuts through cells at every angle
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+        let (dx, dy) = (x as i32 - 32, y as i32 - 32);
+        if dx * dx + dy * dy < 24 * 24 { Rgb([255; 3]) } else { Rgb([0; 3]) }
+    }));
+    let opts = RenderOptions {
+        mode: Mode::Braille,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(8)),
+        ..Default::default()
+    };
+    let crisp = Mode::Braille.render(&img, &opts);
+    let soft = Mode::Braille.render(&img, &RenderOptions { antialias_edges: true, ..opts });
+
+    let mut dimmed = 0;
+    for (crisp, soft) in crisp.iter().flatten().zip(soft.iter().flatten()) {
+        assert_eq!(crisp.glyph, soft.glyph, "the dots stay put");
+        let (before, after) = (crisp.fg.unwrap(), soft.fg.unwrap());
+        if crisp.glyph == '\u{28ff}' {
+            assert_eq!(before, after, "full cells keep their color");
+        } else if before != after {
+            assert!(after.0 < before.0, "{:?} brightened to {:?}", before, after);
+            dimmed += 1;
+        }
+    }
+    assert!(dimmed > 0, "no edge cell was softened");
+}
+
+#[test]
+fn ordered_dither_tiles_over_flat_areas() {
+    // 8x8 Bayer tiles are 4 cells across and 2 down
+    let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 64, Rgb([150; 3])));
+    let opts = RenderOptions {
+        mode: Mode::Braille,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(8)),
+        ordered: true,
+        ..Default::default()
+    };
+
+    let glyphs: Vec<Vec<char>> = Mode::Braille.render(&img, &opts).iter().map(|row| row.iter().map(|cell| cell.glyph).collect()).collect();
+    assert!(glyphs.iter().flatten().any(|&g| g != '\u{2800}' && g != '\u{28ff}'), "flat gray dithers");
+    for (y, row) in glyphs.iter().enumerate() {
+        for (x, &glyph) in row.iter().enumerate() {
+            assert_eq!(glyph, glyphs[y % 2][x % 4], "cell ({}, {}) breaks the tile", x, y);
+        }
+    }
+}
+
+#[test]
+fn luma_gamma_changes_the_dots_but_not_their_colors() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, _| Rgb([(x * 4) as u8, (x * 3) as u8, 90])));
+    let render = |luma_gamma| {
+        let opts = RenderOptions {
+            mode: Mode::Braille,
+            width: Some(Dimension::Cells(16)),
+            height: Some(Dimension::Cells(8)),
+            luma_gamma,
+            ..Default::default()
+        };
+        Mode::Braille.render(&img, &opts)
+    };
+    let dots = |grid: &jiv2::cell::Grid| grid.iter().flatten().map(|cell| (cell.glyph as u32 - 0x2800).count_ones()).sum::<u32>();
+    let colors = |grid: &jiv2::cell::Grid| grid.iter().flatten().map(|cell| cell.fg).collect::<Vec<_>>();
+
+    let (dense, default, sparse) = (render(Some(0.15)), render(None), render(Some(0.8)));
+    assert!(dots(&dense) > dots(&default) && dots(&default) > dots(&sparse));
+    assert_eq!(colors(&dense), colors(&default));
+    assert_eq!(colors(&sparse), colors(&default));
+}
+
+#[test]
+fn no_bold_drops_only_the_attribute() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| Rgb([(x * 4) as u8, (y * 4) as u8, 200])));
+    let opts = RenderOptions {
+        mode: Mode::Braille,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(8)),
+        ..Default::default()
+    };
+    let bold = Mode::Braille.render(&img, &opts);
+    let plain = Mode::Braille.render(&img, &RenderOptions { no_bold: true, ..opts });
+
+    assert!(bold.iter().flatten().all(|cell| cell.bold));
+    assert!(plain.iter().flatten().all(|cell| !cell.bold));
+    let unbolded: Vec<_> = bold.iter().flatten().map(|&cell| jiv2::cell::Cell { bold: false, ..cell }).collect();
+    assert_eq!(unbolded, plain.iter().flatten().copied().collect::<Vec<_>>());
+}
+
+#[test]
+fn dot_coords_match_the_unicode_dot_numbering() {
+    // Unicode's dot n is bit n - 1 of the offset from U+2800: dots 1-3 run
+    // down the left column, 4-6 down the right, and 7 and 8 are the bottom row
+    let position = |dot: u32| match dot {
+        1..=3 => (0, dot - 1),
+        4..=6 => (1, dot - 4),
+        7 => (0, 3),
+        _ => (1, 3),
+    };
+    for mask in 0..256u32 {
+        let glyph = char::from_u32(0x2800 + mask).unwrap();
+        let offset = glyph as u32 - 0x2800;
+        let expected: BTreeSet<_> = (1..=8).filter(|dot| offset & (1 << (dot - 1)) != 0).map(position).collect();
+        let mapped: BTreeSet<_> = BRAILLE_COORDS.iter().filter(|&&(_, _, bit)| mask & bit != 0).map(|&(dx, dy, _)| (dx, dy)).collect();
+        assert_eq!(mapped, expected, "{} ({:#04x})", glyph, mask);
+    }
+
+    // A lone lit pixel in the bottom left of a cell is dot 7 in every
+    // braille mode
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(2, 4, |x, y| if (x, y) == (0, 3) { Rgb([255; 3]) } else { Rgb([0; 3]) }));
+    for mode in [Mode::Braille, Mode::Octant, Mode::Hybrid] {
+        let opts = RenderOptions { mode, width: Some(Dimension::Cells(1)), height: Some(Dimension::Cells(1)), ..Default::default() };
+        assert_eq!(mode.render(&img, &opts)[0][0].glyph, '\u{2840}', "{}", mode);
+    }
+}
+
+#[test]
+fn antialias_edges_counts_keyed_cells_as_empty() {
+    // Keyed green on the left, thin stripes lighting a row of dots a cell on the right
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| match (x < 32, y % 8 < 2) {
+        (true, _) => Rgb([0, 255, 0]),
+        (false, true) => Rgb([255; 3]),
+        (false, false) => Rgb([0; 3]),
+    }));
+    let opts = RenderOptions {
+        mode: Mode::Braille,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(8)),
+        keep_color: "#00ff00±8".parse().ok(),
+        ..Default::default()
+    };
+    let crisp = Mode::Braille.render(&img, &opts);
+    let soft = Mode::Braille.render(&img, &RenderOptions { antialias_edges: true, ..opts });
+
+    assert!(crisp[0][0].is_blank(), "the green is keyed out");
+    for (crisp, soft) in crisp.iter().flatten().zip(soft.iter().flatten()) {
+        let dots = (crisp.glyph as u32).wrapping_sub(0x2800).count_ones();
+        // One to four dots a cell, so no two lit cells are far enough apart to make an edge
+        assert!(crisp.is_blank() || (1..=4).contains(&dots), "{} doesn't fit the test", crisp.glyph);
+        assert_eq!(crisp.fg, soft.fg, "{} next to keyed cells was dimmed", crisp.glyph);
+    }
+}
@@ -0,0 +1,72 @@
+//! The cell callback sees exactly what the ANSI writer prints, and the
+//! writer encodes glyphs and ends rows as asked. Panels line up side by side, and the
+//! debug checkerboard alternates cell by cell.
+
+use image::{DynamicImage, Rgb, RgbImage};
+use jiv2::cell::{checker_cells, side_by_side, to_ansi, write_ansi_with, AnsiOptions, Cell, Grid, OutputEncoding};
+use jiv2::{render_cells, render_to_string, Dimension, Mode, RenderOptions};
+
+#[test]
+fn render_cells_visits_the_printed_grid() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(40, 40, |x, y| Rgb([(x * 6) as u8, (y * 6) as u8, 128])));
+    let opts = RenderOptions {
+        mode: Mode::Sextant,
+        width: Some(Dimension::Cells(10)),
+        height: Some(Dimension::Cells(5)),
+        ..Default::default()
+    };
+
+    let mut grid = Grid::new();
+    render_cells(&img, &opts, |row, col, cell| {
+        if col == 0 {
+            grid.push(Vec::new());
+        }
+        assert_eq!((row, col), (grid.len() - 1, grid[row].len()), "cells are visited in order");
+        grid[row].push(*cell);
+    });
+
+    assert!(!grid.is_empty());
+    assert_eq!(to_ansi(&grid), render_to_string(&img, &opts));
+}
+
+#[test]
+fn ascii_escape_writes_utf16_escapes() {
+    let glyph = |glyph| Cell { glyph, ..Cell::blank() };
+    let grid = vec![vec![glyph('a'), glyph('\u{2801}'), glyph('\u{1fb00}')]];
+    let opts = AnsiOptions { encoding: OutputEncoding::AsciiEscape, ..Default::default() };
+
+    let mut out = Vec::new();
+    write_ansi_with(&grid, &opts, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "a\\u2801\\ud83e\\udf00\n");
+}
+
+#[test]
+fn nul_terminated_rows_have_no_newlines() {
+    let glyph = |glyph| Cell { glyph, ..Cell::blank() };
+    let grid = vec![vec![glyph('a'), glyph('b')], vec![glyph('c'), glyph('d')]];
+    let opts = AnsiOptions { nul_terminated: true, ..Default::default() };
+
+    let mut out = Vec::new();
+    write_ansi_with(&grid, &opts, &mut out).unwrap();
+    assert_eq!(out, b"ab\0cd\0");
+}
+
+#[test]
+fn side_by_side_keeps_each_panel_in_its_column() {
+    let glyph = |glyph| Cell { glyph, ..Cell::blank() };
+    let tall = vec![vec![glyph('a')], vec![glyph('b')]];
+    let wide = vec![vec![glyph('x'), glyph('y'), glyph('z')]];
+
+    let grid = side_by_side(&[("one".into(), tall), ("2".into(), wide)], 1);
+    assert_eq!(to_ansi(&grid), "one 2  \na   xyz\nb      \n");
+}
+
+#[test]
+fn checker_cells_shades_alternate_cells() {
+    let cell = |bg| Cell { bg, ..Cell::blank() };
+    let mut grid = vec![vec![cell(Some((10, 10, 10))), cell(Some((200, 200, 200)))], vec![cell(None), cell(Some((10, 10, 10)))]];
+    checker_cells(&mut grid);
+
+    let shaded: Vec<Vec<_>> = grid.iter().map(|row| row.iter().map(|cell| cell.bg.unwrap()).collect()).collect();
+    assert_eq!(shaded, [[(10, 10, 10), (176, 176, 176)], [(24, 24, 24), (10, 10, 10)]]);
+}
@@ -0,0 +1,72 @@
+//! The color conversions stay finite and in range whatever they are fed,
+//! so one bad value can't spread through error diffusion, and cell colors
+//! average as asked.
+
+use jiv2::color::{
+    linear_to_lab, linear_to_oklab, linear_to_srgb, linear_to_srgb_slice, oklab_to_srgb, srgb_to_linear, srgb_to_linear_slice, ColorAverage,
+};
+
+#[test]
+fn conversions_clamp_non_finite_and_out_of_range_values() {
+    for c in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -3.0, 7.5] {
+        for converted in [srgb_to_linear(c), linear_to_srgb(c)] {
+            assert!((0.0..=1.0).contains(&converted), "{} became {}", c, converted);
+        }
+        let (l, a, b) = linear_to_oklab(c, 0.5, c);
+        assert!(l.is_finite() && a.is_finite() && b.is_finite(), "{} became {:?}", c, (l, a, b));
+    }
+    let (r, g, b) = oklab_to_srgb(2.0, 0.4, -0.4);
+    assert!([r, g, b].iter().all(|c| (0.0..=1.0).contains(c)), "{:?}", (r, g, b));
+}
+
+#[test]
+fn averages_in_linear_light_or_oklab() {
+    let (red, green) = ((1.0, 0.0, 0.0), (0.0, 1.0, 0.0));
+    assert_eq!(ColorAverage::Linear.mean(&[red, green], 2.0), (0.5, 0.5, 0.0));
+    // Colors past those given count as black
+    assert_eq!(ColorAverage::Linear.mean(&[red], 2.0), (0.5, 0.0, 0.0));
+
+    // The Oklab mean sits halfway between the two in perceived lightness
+    let (r, g, b) = ColorAverage::Oklab.mean(&[red, green], 2.0);
+    let lightness = |(r, g, b): (f32, f32, f32)| linear_to_oklab(r, g, b).0;
+    let halfway = (lightness(red) + lightness(green)) / 2.0;
+    assert!((lightness((r, g, b)) - halfway).abs() < 0.01, "{:?}", (r, g, b));
+}
+
+#[test]
+fn slice_conversions_match_one_at_a_time() {
+    // Every 8-bit level, a fine ramp through the dark end, and the odd
+    // values, with a count that leaves a few over past the last full batch
+    let mut values: Vec<f32> = (0..=255).map(|v| v as f32 / 255.0).collect();
+    values.extend((0..1000).map(|v| v as f32 * 1e-5));
+    values.extend([f32::NAN, f32::INFINITY, -0.5, 1.5, 0.04045, 0.0031308, 1e-30]);
+
+    for (slice, scalar) in [(srgb_to_linear_slice as fn(&mut [f32]), srgb_to_linear as fn(f32) -> f32), (linear_to_srgb_slice, linear_to_srgb)] {
+        let mut converted = values.clone();
+        slice(&mut converted);
+        for (&v, &c) in values.iter().zip(&converted) {
+            let expected = scalar(v);
+            assert!((c - expected).abs() <= 8.0 * f32::EPSILON * expected.max(f32::MIN_POSITIVE), "{} became {}, not {}", v, c, expected);
+        }
+    }
+}
+
+#[test]
+fn weighted_means_ignore_what_weighs_nothing() {
+    let (red, green) = ((1.0, 0.0, 0.0), (0.0, 1.0, 0.0));
+    for how in [ColorAverage::Linear, ColorAverage::Oklab] {
+        let (r, g, b) = how.weighted_mean(&[red, green], &[1.0, 0.0]).unwrap();
+        assert!((r - 1.0).abs() < 1e-4 && g.abs() < 1e-4 && b.abs() < 1e-4, "{:?}: {:?}", how, (r, g, b));
+        assert_eq!(how.weighted_mean(&[red, green], &[0.0, 0.0]), None);
+    }
+    assert_eq!(ColorAverage::Linear.weighted_mean(&[red, green], &[3.0, 1.0]), Some((0.75, 0.25, 0.0)));
+}
+
+#[test]
+fn cie_lab_spans_black_to_white() {
+    let round = |(l, a, b): (f32, f32, f32)| (l.round(), a.round(), b.round());
+    assert_eq!(round(linear_to_lab(0.0, 0.0, 0.0)), (0.0, 0.0, 0.0));
+    assert_eq!(round(linear_to_lab(1.0, 1.0, 1.0)), (100.0, 0.0, 0.0));
+    // Published values for sRGB red
+    assert_eq!(round(linear_to_lab(1.0, 0.0, 0.0)), (53.0, 80.0, 67.0));
+}
@@ -0,0 +1,177 @@
+//! Error diffusion on a flat gray fixture: dropping the error that spills
+//! past the edge skews the border cells' tone, which the other edge
+//! handlings avoid, and supersampling keeps the overall tone. Colors
+//! reduced to a few bits or to a fixed palette keep it too when their
+//! residual is diffused, and a palette's residual can be diffused in part.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use jiv2::cell::Grid;
+use jiv2::color::{srgb_to_linear, ColorDistance};
+use jiv2::dither::{Dither, EdgeHandling};
+use jiv2::palette::Palette;
+use jiv2::{Dimension, Mode, RenderOptions};
+
+fn gray_options() -> (DynamicImage, RenderOptions) {
+    let gray = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba([150, 150, 150, 255])));
+    let opts = RenderOptions {
+        mode: Mode::Braille,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(8)),
+        ..Default::default()
+    };
+    (gray, opts)
+}
+
+/// Fraction of braille dots that are on.
+fn coverage(grid: &Grid) -> f32 {
+    let cells = grid.iter().flatten();
+    let lit: u32 = cells.clone().map(|cell| (cell.glyph as u32 - 0x2800).count_ones()).sum();
+    lit as f32 / (cells.count() * 8) as f32
+}
+
+/// How far the dot coverage of the border cells strays from the inner cells'.
+fn border_error(edges: EdgeHandling) -> f32 {
+    let (gray, opts) = gray_options();
+    let opts = RenderOptions { edge_handling: edges, ..opts };
+    let grid = Mode::Braille.render(&gray, &opts);
+
+    let (mut border, mut inner) = ((0, 0), (0, 0));
+    for (y, row) in grid.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let on_border = x == 0 || y == 0 || x == row.len() - 1 || y == grid.len() - 1;
+            let tally = if on_border { &mut border } else { &mut inner };
+            tally.0 += (cell.glyph as u32 - 0x2800).count_ones();
+            tally.1 += 8;
+        }
+    }
+    (border.0 as f32 / border.1 as f32 - inner.0 as f32 / inner.1 as f32).abs()
+}
+
+#[test]
+fn renormalize_and_mirror_keep_border_tone_closer_than_drop() {
+    let drop = border_error(EdgeHandling::Drop);
+    let renormalize = border_error(EdgeHandling::Renormalize);
+    let mirror = border_error(EdgeHandling::Mirror);
+
+    assert!(renormalize < drop, "renormalize {} vs drop {}", renormalize, drop);
+    assert!(mirror < drop, "mirror {} vs drop {}", mirror, drop);
+}
+
+#[test]
+fn supersampling_keeps_the_tone() {
+    let (gray, opts) = gray_options();
+    let plain = coverage(&Mode::Braille.render(&gray, &opts));
+    for n in [2, 3] {
+        let supersampled = coverage(&Mode::Braille.render(&gray, &RenderOptions { supersample: n, ..opts.clone() }));
+        assert!((supersampled - plain).abs() < 0.05, "{}x: {} vs {}", n, supersampled, plain);
+    }
+}
+
+#[test]
+fn reduced_bits_land_on_the_levels_and_dithering_keeps_the_tone() {
+    let (gray, opts) = gray_options();
+    let mean_red = |dither| {
+        let opts = RenderOptions { mode: Mode::Quadrant, bits: Some(2), dither, ..opts.clone() };
+        let colors: Vec<_> = Mode::Quadrant.render(&gray, &opts).into_iter().flatten().flat_map(|cell| cell.fg).collect();
+        assert!(colors.iter().all(|&(r, g, b)| [r, g, b].iter().all(|c| c % 85 == 0)), "{:?} leaves 2-bit levels", dither);
+        colors.iter().map(|&(r, _, _)| r as f32).sum::<f32>() / colors.len() as f32
+    };
+
+    // 150 falls between the 2-bit levels 170 and 85, nearer 170
+    assert_eq!(mean_red(Dither::None), 170.0);
+    let dithered = mean_red(Dither::FloydSteinberg);
+    assert!((dithered - 150.0).abs() < 5.0, "dithered mean {}", dithered);
+}
+
+#[test]
+fn palette_colors_come_from_the_palette_and_dithering_keeps_the_light() {
+    let (gray, opts) = gray_options();
+    let palette: Palette = "GIMP Palette\nName: mono\n# comment\n0 0 0 black\n#fff\n".parse().unwrap();
+    assert_eq!(palette.colors(), [(0, 0, 0), (255, 255, 255)]);
+
+    let white_share = |dither| {
+        let opts = RenderOptions {
+            mode: Mode::Quadrant,
+            palette: Some(palette.clone()),
+            dither,
+            edge_handling: EdgeHandling::Renormalize,
+            ..opts.clone()
+        };
+        let colors: Vec<_> = Mode::Quadrant.render(&gray, &opts).into_iter().flatten().flat_map(|cell| [cell.fg, cell.bg]).flatten().collect();
+        assert!(colors.iter().all(|c| palette.colors().contains(c)), "{:?} leaves the palette", dither);
+        colors.iter().filter(|&&c| c == (255, 255, 255)).count() as f32 / colors.len() as f32
+    };
+
+    // Perceptually 150 is nearer white; in light it's under a third of it.
+    // Renormalized edges, so no error is lost past the borders
+    assert_eq!(white_share(Dither::None), 1.0);
+    let dithered = white_share(Dither::FloydSteinberg);
+    let light = srgb_to_linear(150.0 / 255.0);
+    assert!((dithered - light).abs() < 0.02, "white share {} for light {}", dithered, light);
+}
+
+#[test]
+fn palette_dither_strength_scales_the_spread_error() {
+    let (gray, opts) = gray_options();
+    let palette: Palette = "#000\n#fff\n".parse().unwrap();
+    let white_share = |strength| {
+        let opts = RenderOptions {
+            mode: Mode::Quadrant,
+            palette: Some(palette.clone()),
+            palette_dither_strength: strength,
+            edge_handling: EdgeHandling::Renormalize,
+            ..opts.clone()
+        };
+        let colors: Vec<_> = Mode::Quadrant.render(&gray, &opts).into_iter().flatten().flat_map(|cell| [cell.fg, cell.bg]).flatten().collect();
+        colors.iter().filter(|&&c| c == (255, 255, 255)).count() as f32 / colors.len() as f32
+    };
+
+    // None of the error spreads at 0, so every cell rounds to white, and
+    // half of it lands between that and the full dither
+    assert_eq!(white_share(Some(0.0)), 1.0);
+    let (half, full) = (white_share(Some(0.5)), white_share(None));
+    assert_eq!(white_share(Some(1.0)), full);
+    assert!(full < half && half < 1.0, "white share {} at half strength, {} at full", half, full);
+}
+
+#[test]
+fn palette_matching_follows_the_color_distance() {
+    // A dark slate blue that's nearer black in RGB but nearer blue to the eye
+    let slate = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba([40, 40, 100, 255])));
+    let palette: Palette = "#000\n#00f\n".parse().unwrap();
+    let colors = |color_distance| {
+        let opts = RenderOptions { mode: Mode::Quadrant, palette: Some(palette.clone()), color_distance, dither: Dither::None, ..gray_options().1 };
+        let grid = Mode::Quadrant.render(&slate, &opts);
+        grid.into_iter().flatten().flat_map(|cell| [cell.fg, cell.bg]).flatten().collect::<std::collections::HashSet<_>>()
+    };
+    assert_eq!(colors(ColorDistance::Rgb), [(0, 0, 0)].into());
+    assert_eq!(colors(ColorDistance::Oklab), [(0, 0, 255)].into());
+    // Lab stretches chroma far more than lightness, leaving pure blue distant
+    assert_eq!(colors(ColorDistance::Cie76), [(0, 0, 0)].into());
+}
+
+#[test]
+fn serpentine_octant_changes_the_pattern_but_keeps_the_color() {
+    // Bright speckles on a color ramp, at one pixel per dot so resizing
+    // doesn't blur them: the dots can't be boosted enough to show them, so
+    // error is left to spread
+    let ramp = DynamicImage::ImageRgba8(RgbaImage::from_fn(32, 32, |x, y| {
+        let speckle = if (x * 7 + y * 13) % 5 == 0 { 255 } else { 40 };
+        Rgba([speckle, (x * 8) as u8, (y * 8) as u8, 255])
+    }));
+    let (_, opts) = gray_options();
+    let opts = RenderOptions { mode: Mode::Octant, ..opts };
+    let mean_fg = |grid: &Grid| {
+        let colors: Vec<_> = grid.iter().flatten().flat_map(|cell| cell.fg).collect();
+        let sum = colors.iter().fold([0.0; 3], |sum, &(r, g, b)| [sum[0] + r as f32, sum[1] + g as f32, sum[2] + b as f32]);
+        sum.map(|c| c / colors.len() as f32)
+    };
+
+    let raster = Mode::Octant.render(&ramp, &opts);
+    let serpentine = Mode::Octant.render(&ramp, &RenderOptions { serpentine: true, ..opts });
+    assert!(raster != serpentine, "serpentine changed nothing");
+    let (raster, serpentine) = (mean_fg(&raster), mean_fg(&serpentine));
+    for c in 0..3 {
+        assert!((raster[c] - serpentine[c]).abs() < 3.0, "{:?} vs {:?}", raster, serpentine);
+    }
+}
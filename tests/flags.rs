@@ -0,0 +1,311 @@
+//! Every mode must honor every shared option: each one is checked to change
+//! the output of every mode, so a flag can't be silently dropped by one.
+
+use clap::{Parser, ValueEnum};
+use image::codecs::gif::{GifDecoder, GifEncoder};
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::{AnimationDecoder, DynamicImage, ExtendedColorType, Frame, ImageFormat, Rgba, RgbaImage};
+use jiv2::color::Checker;
+use jiv2::{render_to_string, Dimension, Mode, RenderOptions, SharpenOrder};
+
+/// A colorful test card with fine detail and a half-transparent right side.
+fn test_image() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, y| {
+        let checker = if (x / 2 + y / 2) % 2 == 0 { 255 } else { 0 };
+        let alpha = if x < 32 { 255 } else { 96 };
+        Rgba([(x * 4) as u8, checker, (y * 4) as u8, alpha])
+    }))
+}
+
+fn base_options(mode: Mode) -> RenderOptions {
+    RenderOptions {
+        mode,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(8)),
+        ..Default::default()
+    }
+}
+
+/// The command line, for running whole captures.
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    args: jiv2::cli::Args,
+}
+
+/// A shared option, paired with a change to it that must show in the output.
+type Variation = (&'static str, fn(&mut RenderOptions));
+
+fn variations() -> Vec<Variation> {
+    vec![
+        ("edges", |o| o.edges = true),
+        ("edges --sharpen-order before", |o| {
+            o.edges = true;
+            o.sharpen_order = SharpenOrder::Before;
+        }),
+        ("invert", |o| o.invert = true),
+        ("background", |o| o.background = Some((255, 255, 255))),
+        ("checker-transparency", |o| o.checker = Some(Checker::default())),
+        ("width", |o| o.width = Some(Dimension::Cells(8))),
+        ("height", |o| o.height = Some(Dimension::Cells(4))),
+        ("max-width", |o| o.max_width = Some(Dimension::Cells(8))),
+        ("max-height", |o| o.max_height = Some(Dimension::Cells(4))),
+        ("gamma-resize", |o| o.gamma_resize = true),
+        ("repeat", |o| o.repeat = true),
+        ("pixel-aspect", |o| o.pixel_aspect = Some(2.0)),
+    ]
+}
+
+#[test]
+fn every_mode_honors_every_shared_option() {
+    let img = test_image();
+    let mut missing = Vec::new();
+
+    for &mode in Mode::value_variants() {
+        let base = render_to_string(&img, &base_options(mode));
+        for (flag, apply) in variations() {
+            let mut opts = base_options(mode);
+            apply(&mut opts);
+            if render_to_string(&img, &opts) == base {
+                missing.push(format!("--{} in {}", flag, mode));
+            }
+        }
+    }
+
+    assert!(missing.is_empty(), "options with no effect: {}", missing.join(", "));
+}
+
+#[test]
+fn checker_shows_only_through_transparent_areas() {
+    let img = test_image();
+    let opts = RenderOptions { checker: Some(Checker::default()), ..base_options(Mode::HalfBlock) };
+    let (plain, checkered) = (Mode::HalfBlock.render(&img, &base_options(Mode::HalfBlock)), Mode::HalfBlock.render(&img, &opts));
+    for (plain_row, checkered_row) in plain.iter().zip(&checkered) {
+        // The opaque left half, clear of the resampled seam
+        let quarter = plain_row.len() / 4;
+        assert_eq!(plain_row[..quarter], checkered_row[..quarter]);
+    }
+
+    // A fully transparent image is all squares, in both grays
+    let clear = DynamicImage::ImageRgba8(RgbaImage::new(64, 64));
+    let checker = Checker::default();
+    let colors: Vec<_> = Mode::HalfBlock.render(&clear, &opts).into_iter().flatten().flat_map(|cell| [cell.fg, cell.bg]).collect();
+    assert!(colors.iter().all(|&c| c == Some(checker.dark) || c == Some(checker.light)), "{:?}", colors);
+    assert!(colors.contains(&Some(checker.dark)) && colors.contains(&Some(checker.light)));
+}
+
+#[test]
+fn stretch_fills_the_whole_box_in_every_mode() {
+    let img = test_image();
+    for &mode in Mode::value_variants() {
+        let opts = RenderOptions {
+            width: Some(Dimension::Cells(20)),
+            height: Some(Dimension::Cells(4)),
+            stretch: true,
+            ..base_options(mode)
+        };
+        let grid = mode.render(&img, &opts);
+        assert_eq!(grid.len(), 4, "{} rows", mode);
+        assert!(grid.iter().all(|row| row.len() == 20), "{} columns", mode);
+    }
+}
+
+#[test]
+fn double_width_halves_the_cells_but_keeps_the_shape_on_screen() {
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba([200, 100, 50, 255])));
+    for &mode in Mode::value_variants() {
+        let opts = RenderOptions { width: Some(Dimension::Cells(32)), height: Some(Dimension::Cells(32)), ..base_options(mode) };
+        let single = mode.render(&img, &opts);
+        let double = mode.render(&img, &RenderOptions { double_width: true, ..opts });
+        assert_eq!(double[0].len(), single[0].len() / 2, "{} columns", mode);
+        // Two screen columns per cell: as many columns on screen, as many rows
+        assert!(double.len().abs_diff(single.len()) <= 1, "{} rows: {} vs {}", mode, double.len(), single.len());
+    }
+}
+
+#[test]
+fn invert_diffusion_flips_what_the_glyphs_stand_for() {
+    // White on the left, black on the right
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, _| {
+        if x < 32 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) }
+    }));
+    let ends = |mode: Mode, invert_diffusion| {
+        let grid = mode.render(&img, &RenderOptions { invert_diffusion, ..base_options(mode) });
+        (grid[0][0], *grid[0].last().unwrap())
+    };
+
+    for (mode, bright, dark) in [(Mode::Braille, '⣿', '⠀'), (Mode::Eighths, '█', ' '), (Mode::Ascii, ' ', '@')] {
+        let (left, right) = ends(mode, false);
+        assert_eq!((left.glyph, right.glyph), (bright, dark), "{}", mode);
+        let (left, right) = ends(mode, true);
+        assert_eq!((left.glyph, right.glyph), (dark, bright), "{} inverted", mode);
+    }
+
+    // Block cells draw the other group, in its color, so they look the same
+    for (mode, full, empty) in [(Mode::Octant, '⣿', '⠀'), (Mode::Sextant, '█', ' '), (Mode::Quadrant, '█', ' ')] {
+        let (white, _) = ends(mode, false);
+        let (inverted, _) = ends(mode, true);
+        assert_eq!((white.glyph, white.fg), (full, Some((255, 255, 255))), "{}", mode);
+        assert_eq!((inverted.glyph, inverted.bg), (empty, Some((255, 255, 255))), "{} inverted", mode);
+    }
+}
+
+#[test]
+fn min_contrast_flattens_faint_cells_only() {
+    // Faint grain on the left, hard-edged squares on the right
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, y| {
+        let gray = match x < 32 {
+            true if (x + y) % 2 == 0 => 124,
+            true => 132,
+            false if (x / 8 + y / 8) % 2 == 0 => 255,
+            false => 0,
+        };
+        Rgba([gray, gray, gray, 255])
+    }));
+    let opts = base_options(Mode::Quadrant);
+    let glyphs = |grid: &jiv2::cell::Grid, range: std::ops::Range<usize>| {
+        grid.iter().flat_map(|row| row[range.clone()].iter().map(|cell| cell.glyph)).collect::<String>()
+    };
+    let grainy = Mode::Quadrant.render(&img, &opts);
+    assert!(glyphs(&grainy, 0..8).chars().any(|glyph| !matches!(glyph, ' ' | '█')));
+
+    let flat = Mode::Quadrant.render(&img, &RenderOptions { min_contrast: Some(0.1), ..opts });
+    for cell in flat.iter().flat_map(|row| &row[..8]) {
+        assert_eq!((cell.glyph, cell.fg), (' ', None));
+        let (r, g, b) = cell.bg.unwrap();
+        assert!(r == g && g == b && (124..=132).contains(&r), "{:?}", cell.bg);
+    }
+    // Ringing from the resize flattens out too, but the edges keep their glyphs
+    let (flat, grainy) = (glyphs(&flat, 8..16), glyphs(&grainy, 8..16));
+    assert!(flat.chars().any(|glyph| glyph != ' '));
+    assert!(flat.chars().zip(grainy.chars()).all(|(flat, grainy)| flat == ' ' || flat == grainy));
+}
+
+#[test]
+fn alpha_weighting_keeps_transparent_pixels_out_of_cell_colors() {
+    // Opaque red columns between invisible green ones, as wide as the
+    // output so the fit leaves them be
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(32, 32, |x, _| {
+        if x % 2 == 0 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 255, 0, 0]) }
+    }));
+    for mode in [Mode::Braille, Mode::Octant, Mode::Sextant, Mode::Quadrant] {
+        let green = |alpha_weight| {
+            let grid = mode.render(&img, &RenderOptions { alpha_weight, ..base_options(mode) });
+            let colors = grid.iter().flatten().flat_map(|cell| [cell.fg, cell.bg]).flatten();
+            colors.map(|(_, g, _)| g as u32).sum::<u32>()
+        };
+        assert!(green(false) > 0, "{}", mode);
+        assert_eq!(green(true), 0, "{}", mode);
+    }
+}
+
+#[test]
+fn alpha_weighting_reaches_the_colorless_modes() {
+    // Opaque gray on the left, invisible white or black on the right
+    let half = |hidden: u8| {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(32, 32, |x, _| {
+            if x < 16 { Rgba([128, 128, 128, 255]) } else { Rgba([hidden, hidden, hidden, 0]) }
+        }))
+    };
+    for mode in [Mode::Ascii, Mode::Eighths] {
+        // The quarter at each side, clear of the seam the resize blurs
+        let sides = |hidden, alpha_weight| {
+            let grid = mode.render(&half(hidden), &RenderOptions { alpha_weight, ..base_options(mode) });
+            let quarter = grid[0].len() / 4;
+            let side = |cells: &[jiv2::cell::Cell]| cells.iter().map(|cell| cell.glyph).collect::<String>();
+            grid.iter().map(|row| (side(&row[..quarter]), side(&row[row.len() - quarter..]))).collect::<Vec<_>>()
+        };
+        assert_ne!(sides(255, false), sides(0, false), "{}", mode);
+        let (white, black) = (sides(255, true), sides(0, true));
+        assert_eq!(white, black, "{}: the invisible half changed the glyphs", mode);
+        assert!(white.iter().all(|(_, hidden)| hidden.trim().is_empty()), "{}: the invisible half was drawn", mode);
+    }
+}
+
+#[test]
+fn reserve_lines_sets_the_rows_left_below() {
+    // Tall enough that the terminal's height is what limits it
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 800, Rgba([90, 90, 90, 255])));
+    let rows = |reserve_lines| {
+        let opts = RenderOptions { mode: Mode::HalfBlock, reserve_lines, ..Default::default() };
+        Mode::HalfBlock.render(&img, &opts).len()
+    };
+    assert_eq!(rows(Some(0)), rows(None) + jiv2::RESERVED_LINES as usize);
+    assert_eq!(rows(Some(5)), rows(None) - 3);
+}
+
+#[test]
+fn weighted_resize_keeps_thin_highlights() {
+    // A one-pixel white wire on black, shrunk eight times over
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(256, 256, |x, _| {
+        if x == 100 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) }
+    }));
+    let brightest = |weighted_resize| {
+        let opts = RenderOptions { weighted_resize, ..base_options(Mode::HalfBlock) };
+        let grid = Mode::HalfBlock.render(&img, &opts);
+        grid.iter().flatten().flat_map(|cell| [cell.fg, cell.bg]).flatten().map(|(r, _, _)| r).max().unwrap()
+    };
+    let (plain, weighted) = (brightest(false), brightest(true));
+    assert!(plain < 128, "the plain fit already keeps the wire at {}", plain);
+    assert!(weighted > 240, "weighted fit dims the wire to {}", weighted);
+
+    // Flat areas have nothing standing out, so they come out the same
+    let gray = DynamicImage::ImageRgba8(RgbaImage::from_pixel(256, 256, Rgba([90, 120, 150, 255])));
+    let opts = base_options(Mode::Sextant);
+    assert_eq!(
+        render_to_string(&gray, &RenderOptions { weighted_resize: true, ..opts.clone() }),
+        render_to_string(&gray, &opts)
+    );
+}
+
+#[test]
+fn max_frames_caps_gif_captures() {
+    let dir = std::env::temp_dir().join(format!("jiv2-max-frames-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (input, output) = (dir.join("in.gif"), dir.join("out.gif"));
+    let frames = (0..5).map(|i| Frame::new(RgbaImage::from_pixel(16, 16, Rgba([i * 50, 0, 0, 255]))));
+    GifEncoder::new(std::fs::File::create(&input).unwrap()).encode_frames(frames).unwrap();
+
+    let captured = |max_frames: &str| {
+        let mut argv = vec!["jiv", "--width", "4", "--height", "2", "--gif-out", output.to_str().unwrap(), input.to_str().unwrap()];
+        if !max_frames.is_empty() {
+            argv.extend(["--max-frames", max_frames]);
+        }
+        jiv2::cli::run(&Cli::parse_from(argv).args, Mode::HalfBlock).unwrap();
+        let decoder = GifDecoder::new(std::io::BufReader::new(std::fs::File::open(&output).unwrap())).unwrap();
+        decoder.into_frames().count()
+    };
+    let (all, capped) = (captured(""), captured("2"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!((all, capped), (5, 2));
+}
+
+#[test]
+fn directory_slides_decode_like_their_files() {
+    // A 16px red and a 64px blue icon, alone in a directory
+    let pngs: Vec<_> = [(16, [255, 0, 0, 255]), (64, [0, 0, 255, 255])]
+        .into_iter()
+        .map(|(size, color)| {
+            let mut png = Vec::new();
+            RgbaImage::from_pixel(size, size, Rgba(color)).write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png).unwrap();
+            (size, png)
+        })
+        .collect();
+    let frames: Vec<_> = pngs.iter().map(|(size, png)| IcoFrame::with_encoded(png, *size, *size, ExtendedColorType::Rgba8).unwrap()).collect();
+    let dir = std::env::temp_dir().join(format!("jiv2-slides-{}", std::process::id()));
+    let icons = dir.join("icons");
+    std::fs::create_dir_all(&icons).unwrap();
+    IcoEncoder::new(std::fs::File::create(icons.join("app.ico")).unwrap()).encode_images(&frames).unwrap();
+
+    // --ico-size picks the red one from the directory, as from the file
+    let output = dir.join("out.png");
+    let red = |input: &std::path::Path| {
+        let argv = ["jiv", "--width", "4", "--height", "2", "--ico-size", "16", "--png-out", output.to_str().unwrap(), input.to_str().unwrap()];
+        jiv2::cli::run(&Cli::parse_from(argv).args, Mode::HalfBlock).unwrap();
+        image::open(&output).unwrap().to_rgba8().get_pixel(0, 0).0
+    };
+    let (from_file, from_dir) = (red(&icons.join("app.ico")), red(&icons));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(from_file, [255, 0, 0, 255]);
+    assert_eq!(from_dir, from_file);
+}
@@ -0,0 +1,22 @@
+//! A flat image round-trips its color: every cell of the block modes is
+//! drawn in the source color, whichever way each mode averages and mixes.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use jiv2::{Dimension, Mode, RenderOptions};
+
+#[test]
+fn block_modes_keep_a_flat_color() {
+    for color in [(128, 128, 128), (200, 60, 30)] {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba([color.0, color.1, color.2, 255])));
+        for mode in [Mode::Octant, Mode::Sextant, Mode::Quadrant] {
+            let opts = RenderOptions { mode, width: Some(Dimension::Cells(16)), height: Some(Dimension::Cells(8)), ..Default::default() };
+            for cell in mode.render(&img, &opts).iter().flatten() {
+                for drawn in [cell.fg, cell.bg] {
+                    let (r, g, b) = drawn.unwrap_or_else(|| panic!("{} left a color out", mode));
+                    let off = r.abs_diff(color.0).max(g.abs_diff(color.1)).max(b.abs_diff(color.2));
+                    assert!(off <= 1, "{} drew {:?} for {:?}", mode, (r, g, b), color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! The formats older and scientific pipelines produce decode, and are
+//! picked up from directories; icons and cursors decode at the size that
+//! suits the output, and large stills can shrink toward it as they decode.
+
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::{DynamicImage, ExtendedColorType, ImageFormat, Rgb, Rgb32FImage, RgbImage, RgbaImage};
+use jiv2::frames::{frames_from_memory, list_images, open_image, supported_formats};
+use jiv2::icon::IconEntry;
+use jiv2::tonemap::{to_rgba8, Tonemap};
+
+#[test]
+fn retro_formats_decode_and_list() {
+    let dir = std::env::temp_dir().join(format!("jiv2-formats-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let img = RgbImage::from_fn(4, 3, |x, y| Rgb([(x * 60) as u8, (y * 80) as u8, 7]));
+
+    let files = [("scan.bmp", ImageFormat::Bmp), ("scan.tga", ImageFormat::Tga), ("scan.ppm", ImageFormat::Pnm)];
+    for (name, format) in files {
+        let path = dir.join(name);
+        img.save_with_format(&path, format).unwrap();
+        assert_eq!(open_image(&path).unwrap().to_rgb8(), img, "{} round-trips", name);
+    }
+
+    let listed = list_images(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(listed.len(), files.len());
+
+    for ext in ["bmp", "tga", "pbm"] {
+        assert!(supported_formats().split(", ").any(|f| f == ext), "{} is listed as supported", ext);
+    }
+}
+
+#[test]
+fn hdr_highlights_are_tone_mapped() {
+    let path = std::env::temp_dir().join(format!("jiv2-hdr-{}.hdr", std::process::id()));
+    let levels = [0.0, 0.5, 4.0, 100.0];
+    Rgb32FImage::from_fn(4, 1, |x, _| Rgb([levels[x as usize]; 3])).save(&path).unwrap();
+    let img = open_image(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(img, DynamicImage::ImageRgb32F(_)), "HDR decodes as floating point");
+
+    let red = |tonemap| to_rgba8(img.clone(), tonemap).pixels().map(|p| p[0]).collect::<Vec<_>>();
+    let clipped = red(Tonemap::Clip);
+    assert_eq!((clipped[0], clipped[2], clipped[3]), (0, 255, 255), "clip blows out the highlights");
+    for tonemap in [Tonemap::Reinhard, Tonemap::Aces] {
+        let mapped = red(tonemap);
+        assert!(mapped.windows(2).all(|w| w[0] < w[1]), "{:?} keeps highlights apart: {:?}", tonemap, mapped);
+    }
+}
+
+#[test]
+fn icons_decode_the_size_that_fits() {
+    // A 16px red, 32px green and 64px blue icon
+    let sizes = [(16, [255, 0, 0, 255]), (32, [0, 255, 0, 255]), (64, [0, 0, 255, 255])];
+    let pngs: Vec<Vec<u8>> = sizes
+        .iter()
+        .map(|&(size, color)| {
+            let mut png = Vec::new();
+            RgbaImage::from_pixel(size, size, image::Rgba(color)).write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png).unwrap();
+            png
+        })
+        .collect();
+    let frames: Vec<_> = sizes.iter().zip(&pngs).map(|(&(size, _), png)| IcoFrame::with_encoded(png, size, size, ExtendedColorType::Rgba8).unwrap()).collect();
+    let mut ico = Vec::new();
+    IcoEncoder::new(&mut ico).encode_images(&frames).unwrap();
+
+    let decoded = |bytes: &[u8], pick| {
+        let frame = frames_from_memory(bytes.to_vec(), Tonemap::default(), pick, None).unwrap().next().unwrap().unwrap();
+        let img = frame.into_buffer();
+        (img.width(), img.get_pixel(0, 0).0)
+    };
+    assert_eq!(decoded(&ico, IconEntry::Largest), (64, [0, 0, 255, 255]));
+    assert_eq!(decoded(&ico, IconEntry::Nearest(30)), (32, [0, 255, 0, 255]));
+    assert_eq!(decoded(&ico, IconEntry::Nearest(1)), (16, [255, 0, 0, 255]));
+    assert_eq!(decoded(&ico, IconEntry::Fit(20, 120)), (32, [0, 255, 0, 255]));
+    // Nothing fills the box, so the biggest is scaled up the least
+    assert_eq!(decoded(&ico, IconEntry::Fit(100, 100)).0, 64);
+
+    // A cursor is an icon with a different type, and is known by its extension
+    let mut cur = ico.clone();
+    cur[2] = 2;
+    assert_eq!(decoded(&cur, IconEntry::Nearest(16)).0, 16);
+    let dir = std::env::temp_dir().join(format!("jiv2-icons-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("pointer.cur"), &cur).unwrap();
+    let opened = open_image(&dir.join("pointer.cur")).map(|img| img.width());
+    let listed = list_images(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(opened.unwrap(), 64);
+    assert_eq!(listed.len(), 1);
+    assert!(supported_formats().split(", ").any(|f| f == "cur"));
+}
+
+#[test]
+fn large_stills_shrink_while_decoding() {
+    // Left half a fine black and white checker, right half half-transparent red
+    let img = RgbaImage::from_fn(320, 160, |x, y| match (x < 160, (x + y) % 2 == 0) {
+        (true, true) => image::Rgba([255, 255, 255, 255]),
+        (true, false) => image::Rgba([0, 0, 0, 255]),
+        (false, _) => image::Rgba([200, 0, 0, 128]),
+    });
+    for format in [ImageFormat::Png, ImageFormat::Tiff] {
+        let mut bytes = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut bytes), format).unwrap();
+        let decoded = |shrink| {
+            let frame = frames_from_memory(bytes.clone(), Tonemap::default(), IconEntry::default(), shrink).unwrap().next().unwrap();
+            frame.unwrap().into_buffer()
+        };
+        assert_eq!(decoded(None).dimensions(), (320, 160));
+
+        // Eight to one, which keeps twice the 20x10 box
+        let shrunk = decoded(Some((20, 10)));
+        assert_eq!(shrunk.dimensions(), (40, 20), "{:?}", format);
+        // The checker averages in linear light, to the sRGB gray of half
+        let gray = shrunk.get_pixel(5, 5).0;
+        assert!(gray[..3].iter().all(|&c| c.abs_diff(188) <= 1) && gray[3] == 255, "{:?}: {:?}", format, gray);
+        assert_eq!(shrunk.get_pixel(30, 15).0, [200, 0, 0, 128], "{:?}", format);
+
+        // Too small to be worth shrinking
+        assert_eq!(decoded(Some((200, 100))).dimensions(), (320, 160));
+    }
+}
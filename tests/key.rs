@@ -0,0 +1,58 @@
+//! Keyed-out areas come out as blank cells in every mode.
+
+use clap::ValueEnum;
+use image::{DynamicImage, Rgb, RgbImage};
+use jiv2::cell::Cell;
+use jiv2::key::KeyColor;
+use jiv2::{Dimension, Mode, RenderOptions};
+
+/// Green on the left half, a gradient on the right.
+fn green_screen() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(64, 32, |x, y| {
+        if x < 32 { Rgb([0, 255, 0]) } else { Rgb([(x * 4) as u8, (y * 8) as u8, 128]) }
+    }))
+}
+
+fn options(mode: Mode) -> RenderOptions {
+    RenderOptions {
+        mode,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(4)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn keep_color_blanks_the_matching_cells() {
+    let img = green_screen();
+    for &mode in Mode::value_variants() {
+        let opts = RenderOptions { keep_color: "#00ff00±8".parse().ok(), ..options(mode) };
+        for row in mode.render(&img, &opts) {
+            // Leave the cells next to the seam, where resampling mixes the two
+            let quarter = row.len() / 4;
+            assert!(row[..quarter].iter().all(|&cell| cell == Cell::blank()), "{} shows the keyed green", mode);
+            assert!(row[row.len() - quarter..].iter().all(|&cell| cell != Cell::blank()), "{} renders the rest", mode);
+        }
+    }
+}
+
+#[test]
+fn luma_key_blanks_dark_cells() {
+    // Black on the left half, white on the right
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 32, |x, _| if x < 32 { Rgb([8, 8, 8]) } else { Rgb([255; 3]) }));
+    for &mode in Mode::value_variants() {
+        let opts = RenderOptions { luma_key: Some(32), ..options(mode) };
+        for row in mode.render(&img, &opts) {
+            let quarter = row.len() / 4;
+            assert!(row[..quarter].iter().all(|&cell| cell == Cell::blank()), "{} shows the keyed black", mode);
+        }
+    }
+}
+
+#[test]
+fn parses_key_colors() {
+    assert_eq!("#00ff00".parse(), Ok(KeyColor { color: (0, 255, 0), tolerance: 0 }));
+    assert_eq!("#00ff00±40".parse(), Ok(KeyColor { color: (0, 255, 0), tolerance: 40 }));
+    assert_eq!("#00ff00+-40".parse(), Ok(KeyColor { color: (0, 255, 0), tolerance: 40 }));
+    assert!("#00ff00±400".parse::<KeyColor>().is_err());
+}
@@ -0,0 +1,71 @@
+//! Modes with fewer sub-pixels across each cell need wider terminals, and
+//! narrow ones get pointed at a mode that packs in more. The hybrid mode
+//! picks its glyphs cell by cell.
+
+use image::DynamicImage;
+use jiv2::cell::{Cell, Grid};
+use jiv2::{GlyphSupport, Mode, RenderOptions, Renderer};
+
+#[test]
+fn minimum_widths_follow_the_cell_size() {
+    assert_eq!(Mode::Braille.min_width(), 40);
+    assert_eq!(Mode::Quadrant.min_width(), 40);
+    assert_eq!(Mode::HalfBlock.min_width(), 80);
+    assert_eq!(Mode::Ascii.min_width(), 80);
+}
+
+#[test]
+fn narrow_terminals_suggest_a_denser_mode_the_terminal_can_show() {
+    assert_eq!(Mode::HalfBlock.suggest_for_width(100, GlyphSupport::Full), None);
+    assert_eq!(Mode::HalfBlock.suggest_for_width(50, GlyphSupport::Full), Some(Mode::Octant));
+    assert_eq!(Mode::HalfBlock.suggest_for_width(50, GlyphSupport::Blocks), Some(Mode::Quadrant));
+    assert_eq!(Mode::Ascii.suggest_for_width(50, GlyphSupport::Ascii), None);
+    // Nothing packs more than two sub-pixels across a cell
+    assert_eq!(Mode::Sextant.suggest_for_width(20, GlyphSupport::Full), None);
+}
+
+/// Blank cells, as many as the image has pixels.
+struct Blank;
+
+impl Renderer for Blank {
+    fn cell_size(&self) -> (u32, u32) {
+        (1, 1)
+    }
+
+    fn render(&self, img: &DynamicImage, _: &RenderOptions) -> Grid {
+        vec![vec![Cell::blank(); img.width() as usize]; img.height() as usize]
+    }
+}
+
+#[test]
+fn custom_renderers_get_the_shared_passes() {
+    let opts = RenderOptions { cell_debug: true, ..Default::default() };
+    let grid = jiv2::render_with(&Blank, &DynamicImage::new_rgb8(2, 2), &opts);
+    let backgrounds: Vec<_> = grid.iter().flatten().map(|cell| cell.bg).collect();
+    assert_eq!(backgrounds, [None, Some((24, 24, 24)), Some((24, 24, 24)), None]);
+}
+
+#[test]
+fn hybrid_dots_only_the_busy_cells() {
+    // Flat slate on the left, a one-pixel checker on the right, at the
+    // output's exact size so nothing is resampled away
+    let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(32, 16, |x, y| match (x < 16, (x + y) % 2 == 0) {
+        (true, _) => image::Rgba([60, 70, 110, 255]),
+        (false, true) => image::Rgba([250, 240, 200, 255]),
+        (false, false) => image::Rgba([20, 10, 30, 255]),
+    }));
+    let opts = RenderOptions {
+        mode: Mode::Hybrid,
+        width: Some(jiv2::Dimension::Cells(16)),
+        height: Some(jiv2::Dimension::Cells(4)),
+        ..Default::default()
+    };
+    let grid = Mode::Hybrid.render(&img, &opts);
+    assert_eq!((grid.len(), grid[0].len()), (4, 16));
+
+    let braille = |glyph: char| ('\u{2800}'..='\u{28ff}').contains(&glyph);
+    for row in &grid {
+        assert!(row[..8].iter().all(|cell| jiv2::quadrant::QUADRANTS.contains(&cell.glyph)), "flat cells are blocks");
+        assert!(row[8..].iter().all(|cell| braille(cell.glyph) && cell.glyph != '\u{2800}'), "busy cells are dots");
+    }
+}
@@ -0,0 +1,44 @@
+//! Posterizing to two bands gives a strict two-tone print: no dither
+//! pattern in the shapes, and only the two duotone colors.
+
+use image::{DynamicImage, Rgb, RgbImage};
+use jiv2::cell::Cell;
+use jiv2::{Dimension, Mode, RenderOptions};
+
+/// Dark on the left to bright on the right, the same on every row.
+fn ramp() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, _| Rgb([(x * 4) as u8; 3])))
+}
+
+fn options(mode: Mode) -> RenderOptions {
+    RenderOptions {
+        mode,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(8)),
+        posterize: Some(2),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn two_bands_leave_no_dither_pattern() {
+    let grid = Mode::Braille.render(&ramp(), &options(Mode::Braille));
+    let glyphs: Vec<String> = grid.iter().map(|row| row.iter().map(|cell| cell.glyph).collect()).collect();
+    assert!(glyphs.iter().all(|row| *row == glyphs[0]), "rows differ: {:?}", glyphs);
+    assert!(glyphs[0].starts_with('\u{2800}') && glyphs[0].ends_with('\u{28ff}'), "{}", glyphs[0]);
+}
+
+#[test]
+fn two_bands_with_duotone_give_two_colors() {
+    let (dark, light) = ((20, 30, 90), (250, 220, 160));
+    for mode in [Mode::Braille, Mode::Octant, Mode::Sextant, Mode::Quadrant] {
+        let opts = RenderOptions { duotone: Some((dark, light)), ..options(mode) };
+        let grid = mode.render(&ramp(), &opts);
+        // Braille cells sit on a fixed backdrop; only their dots take image colors
+        let drawn = |cell: &Cell| if mode == Mode::Braille { [cell.fg, None] } else { [cell.fg, cell.bg] };
+        let colors = grid.iter().flatten().flat_map(drawn).flatten();
+        for color in colors {
+            assert!(color == dark || color == light, "{} drew {:?}", mode, color);
+        }
+    }
+}
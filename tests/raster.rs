@@ -0,0 +1,70 @@
+//! `--png-out` and `--gif-out` draw each cell's glyph from the atlas, so
+//! every glyph a mode can emit needs a mask there.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use jiv2::eighths::LEVELS;
+use jiv2::raster::{rasterize, GlyphAtlas};
+use jiv2::{Dimension, Mode, RenderOptions};
+
+/// A left to right gray ramp, 16 cells wide and 4 tall.
+fn ramp(mode: Mode) -> jiv2::cell::Grid {
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(128, 64, |x, _| {
+        let v = (x * 2) as u8;
+        Rgba([v, v, v, 255])
+    }));
+    let opts = RenderOptions {
+        mode,
+        width: Some(Dimension::Cells(16)),
+        height: Some(Dimension::Cells(4)),
+        ..Default::default()
+    };
+    mode.render(&img, &opts)
+}
+
+/// Share of the image's pixels that aren't the black default background.
+fn lit_share(img: &RgbaImage) -> f32 {
+    let lit = img.pixels().filter(|p| p.0[..3] != [0, 0, 0]).count();
+    lit as f32 / img.pixels().len() as f32
+}
+
+#[test]
+fn eighths_fill_their_share_of_the_cell() {
+    let grid = ramp(Mode::Eighths);
+    let img = rasterize(&grid, &mut GlyphAtlas::new(8, 16));
+    // Each glyph lights as many eighths of its cell as its level
+    let levels: usize = grid.iter().flatten().map(|c| LEVELS.iter().position(|&l| l == c.glyph).unwrap()).sum();
+    let expected = levels as f32 / (8 * grid.len() * grid[0].len()) as f32;
+    assert!(expected > 0.2, "ramp rendered too dark: {}", expected);
+    assert_eq!(lit_share(&img), expected);
+}
+
+#[test]
+fn bars_and_shades_cover_their_share() {
+    let mut atlas = GlyphAtlas::new(8, 16);
+    let mut share = |c: char| {
+        let mask = atlas.glyph(c);
+        mask.iter().filter(|&&lit| lit).count() as f32 / mask.len() as f32
+    };
+    for (i, c) in "▁▂▃▄▅▆▇".chars().enumerate() {
+        assert_eq!(share(c), (i + 1) as f32 / 8.0, "{}", c);
+    }
+    for (i, c) in "▏▎▍▌▋▊▉".chars().enumerate() {
+        assert_eq!(share(c), (i + 1) as f32 / 8.0, "{}", c);
+    }
+    for (i, c) in "░▒▓".chars().enumerate() {
+        assert_eq!(share(c), (i + 1) as f32 / 4.0, "{}", c);
+    }
+    // Bars grow from the bottom and the left
+    assert!(atlas.glyph('▁')[8 * 15] && !atlas.glyph('▁')[0]);
+    assert!(atlas.glyph('▏')[0] && !atlas.glyph('▏')[7]);
+}
+
+#[test]
+fn legacy_blocks_glyphs_all_have_masks() {
+    let grid = ramp(Mode::LegacyBlocks);
+    let mut atlas = GlyphAtlas::new(8, 16);
+    for cell in grid.iter().flatten().filter(|c| c.glyph != ' ') {
+        assert!(atlas.glyph(cell.glyph).contains(&true), "{} is blank", cell.glyph);
+    }
+    assert!(lit_share(&rasterize(&grid, &mut atlas)) > 0.2);
+}
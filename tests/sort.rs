@@ -0,0 +1,39 @@
+//! Multiple inputs are ordered by --sort-files: numbers by value by
+//! default, so numbered frame sequences play in order.
+
+use jiv2::frames::SortFiles;
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+fn sorted(order: SortFiles, names: &[&str]) -> Vec<String> {
+    let mut paths: Vec<PathBuf> = names.iter().map(PathBuf::from).collect();
+    order.sort(&mut paths);
+    paths.iter().map(|p| p.display().to_string()).collect()
+}
+
+#[test]
+fn sorts_by_name_or_keeps_the_order() {
+    let names = ["frame10.png", "frame2.png", "frame1.png"];
+    assert_eq!(sorted(SortFiles::Natural, &names), ["frame1.png", "frame2.png", "frame10.png"]);
+    assert_eq!(sorted(SortFiles::Name, &names), ["frame1.png", "frame10.png", "frame2.png"]);
+    assert_eq!(sorted(SortFiles::None, &names), names);
+}
+
+#[test]
+fn sorts_by_modification_time() {
+    let dir = std::env::temp_dir().join(format!("jiv2-sort-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let epoch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let names = ["b.png", "a.png", "c.png"];
+    for (age, name) in [2, 3, 1].iter().zip(names) {
+        let file = File::create(dir.join(name)).unwrap();
+        file.set_modified(epoch - Duration::from_secs(*age * 60)).unwrap();
+    }
+
+    let mut paths: Vec<PathBuf> = names.iter().map(|name| dir.join(name)).collect();
+    SortFiles::Mtime.sort(&mut paths);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let order: Vec<_> = paths.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+    assert_eq!(order, ["a.png", "b.png", "c.png"]);
+}
@@ -0,0 +1,104 @@
+//! The --strip pass only ever shrinks the byte stream: replayed on a model
+//! terminal, the optimized output draws exactly what the original did.
+
+use clap::ValueEnum;
+use image::{DynamicImage, Rgba, RgbaImage};
+use jiv2::cell::{optimize_ansi, write_ansi_with, AnsiOptions};
+use jiv2::{Dimension, Mode, RenderOptions};
+use std::collections::BTreeMap;
+
+/// Foreground, background, bold and basic foreground color (30-37).
+type Attributes = (Option<(u8, u8, u8)>, Option<(u8, u8, u8)>, bool, Option<u8>);
+
+/// What a terminal shows after `ansi`: every cell written or erased, with
+/// the attributes it got.
+fn screen(ansi: &str) -> BTreeMap<(i32, i32), (char, Attributes)> {
+    let mut cells = BTreeMap::new();
+    let (mut row, mut col) = (0, 0);
+    let mut attributes: Attributes = (None, None, false, None);
+    let mut chars = ansi.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                assert_eq!(chars.next(), Some('['));
+                let mut params = String::new();
+                while let Some(p) = chars.next_if(|p| p.is_ascii_digit() || *p == ';') {
+                    params.push(p);
+                }
+                let n = |default| params.parse().unwrap_or(default);
+                match chars.next().unwrap() {
+                    'm' => {
+                        let mut params = params.split(';').map(|p| p.parse::<u8>().unwrap_or(0));
+                        while let Some(param) = params.next() {
+                            match param {
+                                0 => attributes = (None, None, false, None),
+                                1 => attributes.2 = true,
+                                22 => attributes.2 = false,
+                                30..=37 => (attributes.0, attributes.3) = (None, Some(param)),
+                                38 => (attributes.0, attributes.3) = (Some(true_color(&mut params)), None),
+                                39 => (attributes.0, attributes.3) = (None, None),
+                                48 => attributes.1 = Some(true_color(&mut params)),
+                                49 => attributes.1 = None,
+                                _ => panic!("unexpected SGR {}", param),
+                            }
+                        }
+                    }
+                    // Erasing paints the rest of the line in the current background
+                    'K' => {
+                        for c in col..200 {
+                            cells.insert((row, c), (' ', (None, attributes.1, false, None)));
+                        }
+                    }
+                    'F' => (row, col) = (row - n(1), 0),
+                    other => panic!("unexpected escape {}", other),
+                }
+            }
+            '\n' => (row, col) = (row + 1, 0),
+            c => {
+                cells.insert((row, col), (c, attributes));
+                col += 1;
+            }
+        }
+    }
+    cells
+}
+
+/// The `2;r;g;b` that follows a 38 or 48.
+fn true_color(params: &mut impl Iterator<Item = u8>) -> (u8, u8, u8) {
+    assert_eq!(params.next(), Some(2));
+    (params.next().unwrap(), params.next().unwrap(), params.next().unwrap())
+}
+
+#[test]
+fn optimized_output_draws_the_same() {
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(48, 48, |x, y| {
+        let alpha = if x < 24 { 255 } else { 0 };
+        Rgba([(x * 5) as u8, if (x / 3 + y / 3) % 2 == 0 { 255 } else { 40 }, (y * 5) as u8, alpha])
+    }));
+    for &mode in Mode::value_variants() {
+        let opts = RenderOptions { mode, width: Some(Dimension::Cells(12)), height: Some(Dimension::Cells(6)), ..Default::default() };
+        let grid = mode.render(&img, &opts);
+        for layout in [AnsiOptions::default(), AnsiOptions { trim_trailing: true, redraw_rows: 3, ..Default::default() }] {
+            let mut buf = Vec::new();
+            write_ansi_with(&grid, &layout, &mut buf).unwrap();
+            let ansi = String::from_utf8(buf).unwrap();
+            let optimized = optimize_ansi(&ansi);
+            assert!(optimized.len() <= ansi.len(), "{} grew", mode);
+            assert_eq!(screen(&optimized), screen(&ansi), "{} looks different", mode);
+        }
+    }
+}
+
+#[test]
+fn drops_and_merges_only_what_changes_nothing() {
+    // A reset straight before new colors, and a color set twice
+    assert_eq!(optimize_ansi("\x1b[0m\x1b[38;2;1;2;3mx\x1b[38;2;1;2;3my"), "\x1b[38;2;1;2;3mxy");
+    // A run that ends where it started
+    assert_eq!(optimize_ansi("a\x1b[1m\x1b[22mb"), "ab");
+    // A trailing reset is kept, as it changes the state
+    assert_eq!(optimize_ansi("\x1b[1mx\x1b[0m"), "\x1b[1mx\x1b[0m");
+    // Attributes it doesn't model pass through, and what follows is kept whole
+    assert_eq!(optimize_ansi("\x1b[31mx\x1b[39my"), "\x1b[31mx\x1b[39my");
+    let ansi = "\x1b[31mx\x1b[39my\x1b[1mz";
+    assert_eq!(screen(&optimize_ansi(ansi)), screen(ansi));
+}
@@ -0,0 +1,20 @@
+use jiv2::theme::{parse_cursor_column, parse_osc11_reply};
+
+#[test]
+fn parses_background_replies() {
+    assert_eq!(parse_osc11_reply(b"\x1b]11;rgb:ffff/ffff/dddd\x07"), Some((255, 255, 221)));
+    assert_eq!(parse_osc11_reply(b"\x1b]11;rgb:1e1e/1e1e/2e2e\x1b\\"), Some((30, 30, 46)));
+    assert_eq!(parse_osc11_reply(b"\x1b]11;rgb:f/8/0\x07"), Some((255, 136, 0)));
+    assert_eq!(parse_osc11_reply(b"\x1b]11;rgb:ffff/ffff\x07"), None);
+    assert_eq!(parse_osc11_reply(b"\x1b]11;rgb:fffff/0/0\x07"), None);
+    assert_eq!(parse_osc11_reply(b""), None);
+}
+
+#[test]
+fn parses_cursor_position_replies() {
+    assert_eq!(parse_cursor_column(b"\x1b[12;3R"), Some(3));
+    // Input typed before the reply is skipped
+    assert_eq!(parse_cursor_column(b"x\x1b[1;2R"), Some(2));
+    assert_eq!(parse_cursor_column(b"\x1b[12R"), None);
+    assert_eq!(parse_cursor_column(b""), None);
+}
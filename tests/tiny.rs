@@ -0,0 +1,24 @@
+//! Sources smaller than one cell still render in every mode, both at the
+//! default size and scaled up to a set one.
+
+use clap::ValueEnum;
+use image::{DynamicImage, Rgb, RgbImage};
+use jiv2::{Dimension, Mode, RenderOptions};
+
+#[test]
+fn every_mode_renders_tiny_sources() {
+    for (width, height) in [(1, 1), (1, 5), (5, 1)] {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| Rgb([160, 120 + (x * 10) as u8, 120 + (y * 10) as u8])));
+        for &mode in Mode::value_variants() {
+            for size in [None, Some(Dimension::Cells(4))] {
+                let opts = RenderOptions { mode, width: size, height: size, ..Default::default() };
+                let grid = mode.render(&img, &opts);
+                let label = format!("{} from {}x{} at {:?}", mode, width, height, size);
+                assert!(!grid.is_empty() && grid.iter().all(|row| !row.is_empty()), "{} is empty", label);
+                // A mid-tone source must leave some ink in every mode
+                let inked = grid.iter().flatten().any(|cell| !matches!(cell.glyph, ' ' | '\u{2800}'));
+                assert!(inked, "{} shows nothing", label);
+            }
+        }
+    }
+}